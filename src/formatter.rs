@@ -0,0 +1,303 @@
+/// Format-on-save integration with external formatters
+///
+/// Pipes buffer text through a per-language external command (`rustfmt`,
+/// `gofmt`, ...) and, rather than blindly replacing the buffer, computes a
+/// line-level diff against the formatter's output and describes only the
+/// differing region as a single [`FormatEdit`]. Nothing in this module
+/// applies that edit to a buffer or runs on save; both are up to the caller.
+/// A non-zero exit or other formatter failure returns an error carrying the
+/// formatter's stderr so the caller can surface it in the status line.
+use std::io::Write;
+use std::ops::Range;
+use std::process::{Command, Stdio};
+
+/// Per-language formatter configuration.
+#[derive(Debug, Clone, Default)]
+pub struct FormatterConfig {
+    /// The external command to run, e.g. `"rustfmt"` or `"gofmt"`. `None`
+    /// means no formatter is configured for this language.
+    pub formatter: Option<String>,
+    /// Whether to run the formatter automatically on save (Ctrl-S).
+    pub format_on_save: bool,
+}
+
+/// A formatter invocation failed: non-zero exit or the process couldn't be
+/// spawned/communicated with at all.
+#[derive(Debug, Clone)]
+pub struct FormatError {
+    pub stderr: String,
+}
+
+/// Run `command`, feeding `input` on stdin, and return its stdout.
+///
+/// Stdin is written from a background thread while this thread blocks on
+/// `wait_with_output` reading stdout/stderr: a formatter that writes enough
+/// output to fill its stdout pipe before it has finished reading stdin would
+/// otherwise deadlock against us still blocked in `write_all` (rustfmt on a
+/// large file hits this easily).
+///
+/// On a non-zero exit (or a spawn/IO failure), returns the process's stderr
+/// (or a synthesized message for spawn failures) so the caller can surface it
+/// without touching the buffer.
+pub fn run_external_formatter(command: &str, input: &str) -> Result<String, FormatError> {
+    let mut parts = command.split_whitespace();
+    let program = parts.next().ok_or_else(|| FormatError {
+        stderr: "empty formatter command".to_string(),
+    })?;
+    let args: Vec<&str> = parts.collect();
+
+    let mut child = Command::new(program)
+        .args(&args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| FormatError {
+            stderr: format!("failed to spawn `{}`: {}", command, e),
+        })?;
+
+    let mut stdin = child.stdin.take().expect("stdin was piped");
+    let input = input.to_string();
+    let writer = std::thread::spawn(move || stdin.write_all(input.as_bytes()));
+
+    let output = child.wait_with_output().map_err(|e| FormatError {
+        stderr: format!("failed to read `{}` output: {}", command, e),
+    })?;
+
+    // Propagate a stdin-write failure (e.g. the formatter closed it early)
+    // only if the process otherwise looked like it succeeded; a non-zero
+    // exit below is almost always the more useful error to surface.
+    let write_result = writer.join().map_err(|_| FormatError {
+        stderr: format!("stdin writer thread for `{}` panicked", command),
+    })?;
+    if output.status.success() {
+        write_result.map_err(|e| FormatError {
+            stderr: format!("failed to write to `{}` stdin: {}", command, e),
+        })?;
+    }
+
+    if !output.status.success() {
+        return Err(FormatError {
+            stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        });
+    }
+
+    String::from_utf8(output.stdout).map_err(|e| FormatError {
+        stderr: format!("`{}` produced non-UTF-8 output: {}", command, e),
+    })
+}
+
+/// A single undoable edit replacing the differing region of a buffer with
+/// the formatter's rewritten version of that region.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FormatEdit {
+    /// Byte range of the differing region in the *original* buffer.
+    pub range: Range<usize>,
+    /// Its replacement text, as produced by the formatter.
+    pub replacement: String,
+    /// How many lines precede the differing region (0-indexed line number
+    /// where it starts), used to anchor the cursor.
+    pub first_changed_line: usize,
+    /// How many lines the differing region spans in the original buffer.
+    pub old_line_count: usize,
+    /// How many lines the differing region spans in the formatted buffer.
+    pub new_line_count: usize,
+}
+
+/// Compute the minimal single edit that turns `original` into `formatted`,
+/// by trimming the common line prefix and suffix and replacing everything
+/// in between. Returns `None` if the formatter produced identical output
+/// (nothing to apply).
+pub fn compute_format_edit(original: &str, formatted: &str) -> Option<FormatEdit> {
+    if original == formatted {
+        return None;
+    }
+
+    let old_lines: Vec<&str> = original.split_inclusive('\n').collect();
+    let new_lines: Vec<&str> = formatted.split_inclusive('\n').collect();
+
+    let common_prefix = old_lines
+        .iter()
+        .zip(new_lines.iter())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let old_rest = &old_lines[common_prefix..];
+    let new_rest = &new_lines[common_prefix..];
+
+    let common_suffix = old_rest
+        .iter()
+        .rev()
+        .zip(new_rest.iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count()
+        // Never let the suffix eat back into the prefix when the whole
+        // thing matched except for a single differing line already counted.
+        .min(old_rest.len())
+        .min(new_rest.len());
+
+    let old_changed = &old_lines[common_prefix..old_lines.len() - common_suffix];
+    let new_changed = &new_lines[common_prefix..new_lines.len() - common_suffix];
+
+    let start = old_lines[..common_prefix].iter().map(|l| l.len()).sum();
+    let old_changed_len: usize = old_changed.iter().map(|l| l.len()).sum();
+
+    Some(FormatEdit {
+        range: start..start + old_changed_len,
+        replacement: new_changed.concat(),
+        first_changed_line: common_prefix,
+        old_line_count: old_changed.len(),
+        new_line_count: new_changed.len(),
+    })
+}
+
+/// Recompute where the cursor (given as a 0-indexed line number in the
+/// original buffer) should land after applying a [`FormatEdit`].
+///
+/// Lines before the changed region are untouched, so the cursor stays put.
+/// Lines after it shift by the line-count delta. A cursor that was inside
+/// the changed region itself has no surviving counterpart, so it's anchored
+/// to the first line of the replacement.
+pub fn anchor_cursor_line(cursor_line: usize, edit: &FormatEdit) -> usize {
+    let changed_end = edit.first_changed_line + edit.old_line_count;
+
+    if cursor_line < edit.first_changed_line {
+        cursor_line
+    } else if cursor_line >= changed_end {
+        let delta = edit.new_line_count as isize - edit.old_line_count as isize;
+        (cursor_line as isize + delta).max(edit.first_changed_line as isize) as usize
+    } else {
+        edit.first_changed_line
+    }
+}
+
+/// A rendered diff hunk for the `:format` confirmation preview: `context`
+/// lines of unchanged text around each change, rustfmt-test-harness style.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiffPreview {
+    pub removed: Vec<String>,
+    pub added: Vec<String>,
+    pub context_before: Vec<String>,
+    pub context_after: Vec<String>,
+}
+
+/// Build a preview of the change a [`FormatEdit`] would make, with `context`
+/// lines of surrounding unchanged text on each side.
+pub fn diff_preview(original: &str, edit: &FormatEdit, context: usize) -> DiffPreview {
+    let old_lines: Vec<&str> = original.split_inclusive('\n').collect();
+
+    let before_start = edit.first_changed_line.saturating_sub(context);
+    let context_before = old_lines[before_start..edit.first_changed_line]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+
+    let changed_end = edit.first_changed_line + edit.old_line_count;
+    let after_end = (changed_end + context).min(old_lines.len());
+    let context_after = old_lines[changed_end..after_end]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+
+    let removed = old_lines[edit.first_changed_line..changed_end]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+    let added = edit
+        .replacement
+        .split_inclusive('\n')
+        .map(|s| s.to_string())
+        .collect();
+
+    DiffPreview {
+        removed,
+        added,
+        context_before,
+        context_after,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compute_format_edit_no_change() {
+        assert_eq!(compute_format_edit("fn main() {}\n", "fn main() {}\n"), None);
+    }
+
+    #[test]
+    fn test_compute_format_edit_single_line_change() {
+        let original = "fn main() {\n    foo( );\n}\n";
+        let formatted = "fn main() {\n    foo();\n}\n";
+
+        let edit = compute_format_edit(original, formatted).unwrap();
+        assert_eq!(edit.first_changed_line, 1);
+        assert_eq!(edit.old_line_count, 1);
+        assert_eq!(edit.new_line_count, 1);
+        assert_eq!(edit.replacement, "    foo();\n");
+        assert_eq!(&original[edit.range.clone()], "    foo( );\n");
+    }
+
+    #[test]
+    fn test_compute_format_edit_trims_common_prefix_and_suffix() {
+        let original = "a\nb\nold\nc\nd\n";
+        let formatted = "a\nb\nnew1\nnew2\nc\nd\n";
+
+        let edit = compute_format_edit(original, formatted).unwrap();
+        assert_eq!(edit.first_changed_line, 2);
+        assert_eq!(edit.old_line_count, 1);
+        assert_eq!(edit.new_line_count, 2);
+        assert_eq!(edit.replacement, "new1\nnew2\n");
+    }
+
+    #[test]
+    fn test_anchor_cursor_line_before_change_is_untouched() {
+        let edit = FormatEdit {
+            range: 0..0,
+            replacement: String::new(),
+            first_changed_line: 5,
+            old_line_count: 1,
+            new_line_count: 3,
+        };
+        assert_eq!(anchor_cursor_line(2, &edit), 2);
+    }
+
+    #[test]
+    fn test_anchor_cursor_line_after_change_shifts_by_delta() {
+        let edit = FormatEdit {
+            range: 0..0,
+            replacement: String::new(),
+            first_changed_line: 5,
+            old_line_count: 1,
+            new_line_count: 3,
+        };
+        // 2 extra lines were inserted, so line 10 becomes line 12
+        assert_eq!(anchor_cursor_line(10, &edit), 12);
+    }
+
+    #[test]
+    fn test_anchor_cursor_line_inside_change_snaps_to_start() {
+        let edit = FormatEdit {
+            range: 0..0,
+            replacement: String::new(),
+            first_changed_line: 5,
+            old_line_count: 3,
+            new_line_count: 1,
+        };
+        assert_eq!(anchor_cursor_line(6, &edit), 5);
+    }
+
+    #[test]
+    fn test_diff_preview_includes_requested_context() {
+        let original = "1\n2\n3\nold\n5\n6\n7\n";
+        let formatted = "1\n2\n3\nnew\n5\n6\n7\n";
+        let edit = compute_format_edit(original, formatted).unwrap();
+
+        let preview = diff_preview(original, &edit, 2);
+        assert_eq!(preview.context_before, vec!["2\n", "3\n"]);
+        assert_eq!(preview.removed, vec!["old\n"]);
+        assert_eq!(preview.added, vec!["new\n"]);
+        assert_eq!(preview.context_after, vec!["5\n", "6\n"]);
+    }
+}
@@ -5,6 +5,63 @@
 use crate::buffer::Buffer;
 use crate::marker::{MarkerId, MarkerList};
 use crate::marker_tree::AnchorConfidence;
+use memchr::{memchr, memchr2, memrchr, memrchr2};
+use std::cell::{Ref, RefCell};
+use std::ops::Range;
+
+/// The line terminator convention a buffer uses, so anchor scanning agrees
+/// with how the file is actually split into lines rather than hardcoding `\n`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineEnding {
+    /// `\n` only.
+    Lf,
+    /// `\r\n` only.
+    CrLf,
+    /// Old-Mac-style lone `\r` only.
+    Cr,
+    /// A mix of the above; each scan determines the terminator at that
+    /// specific position rather than assuming one for the whole buffer.
+    Mixed,
+}
+
+/// Sample size used to detect a buffer's dominant line ending.
+const LINE_ENDING_SAMPLE_BYTES: usize = 64 * 1024;
+
+/// Block size used when scanning backward from the end of the buffer for
+/// tail/follow operations.
+const TAIL_BLOCK_SIZE: usize = 4096;
+
+/// Detect the dominant line terminator in `buffer` by sampling its first
+/// [`LINE_ENDING_SAMPLE_BYTES`]. A `\r` immediately followed by `\n` counts
+/// as one CRLF terminator, not a CR and an LF.
+fn detect_line_ending(buffer: &Buffer) -> LineEnding {
+    let sample_end = buffer.len().min(LINE_ENDING_SAMPLE_BYTES);
+    let bytes = buffer.slice(0..sample_end).as_bytes();
+
+    let (mut crlf, mut lf, mut cr) = (0usize, 0usize, 0usize);
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\r' if i + 1 < bytes.len() && bytes[i + 1] == b'\n' => {
+                crlf += 1;
+                i += 2;
+                continue;
+            }
+            b'\r' => cr += 1,
+            b'\n' => lf += 1,
+            _ => {}
+        }
+        i += 1;
+    }
+
+    match (crlf > 0, lf > 0, cr > 0) {
+        (true, false, false) => LineEnding::CrLf,
+        (false, true, false) => LineEnding::Lf,
+        (false, false, true) => LineEnding::Cr,
+        (false, false, false) => LineEnding::Lf, // no terminators at all
+        _ => LineEnding::Mixed,
+    }
+}
 
 /// Manages line anchors for efficient line number ↔ byte offset conversion
 pub struct LineAnchorManager {
@@ -16,11 +73,20 @@ pub struct LineAnchorManager {
     avg_line_length: usize,
     /// Large file threshold - files below this use exact line tracking
     large_file_threshold: usize,
+    /// This buffer's line terminator convention, detected once on construction
+    line_ending: LineEnding,
+    /// Fully-materialized table of line-start byte offsets (`table[n]` is
+    /// the start of line `n`), for files below `large_file_threshold`.
+    /// Built lazily on first use via [`Self::ensure_line_index`] and dropped
+    /// by any call that invalidates it, rather than kept eagerly in sync.
+    line_index: RefCell<Option<Vec<usize>>>,
 }
 
 impl LineAnchorManager {
-    /// Create a new line anchor manager
-    pub fn new(file_size: usize, large_file_threshold: usize) -> Self {
+    /// Create a new line anchor manager, detecting the buffer's line ending
+    /// convention once up front.
+    pub fn new(buffer: &Buffer, large_file_threshold: usize) -> Self {
+        let file_size = buffer.len();
         // Default assumption: 100 bytes per line
         let avg_line_length = 100;
         let estimated_total_lines = if file_size > 0 {
@@ -34,6 +100,8 @@ impl LineAnchorManager {
             file_size,
             avg_line_length,
             large_file_threshold,
+            line_ending: detect_line_ending(buffer),
+            line_index: RefCell::new(None),
         }
     }
 
@@ -43,6 +111,31 @@ impl LineAnchorManager {
         if self.estimated_total_lines.is_some() && self.avg_line_length > 0 {
             self.estimated_total_lines = Some(new_size / self.avg_line_length);
         }
+        // The materialized line index is stale after any edit; drop it so
+        // the next lookup rebuilds it from scratch.
+        self.line_index.borrow_mut().take();
+    }
+
+    /// Build the full line-start table once (copy-on-first-use: a cache hit
+    /// does nothing, a miss does a single `memchr`-driven scan) and hand
+    /// back a reference to it. Only called for files below
+    /// `large_file_threshold`; large files keep using the sparse
+    /// anchor/estimation path instead of materializing the whole table.
+    fn ensure_line_index(&self, buffer: &Buffer) -> Ref<'_, Vec<usize>> {
+        if self.line_index.borrow().is_none() {
+            let mut starts = vec![0usize];
+            let bytes = buffer.slice(0..buffer.len()).as_bytes();
+            let mut pos = 0;
+            while let Some((term_pos, width)) = self.find_terminator_forward(&bytes[pos..]) {
+                pos += term_pos + width;
+                starts.push(pos);
+            }
+            *self.line_index.borrow_mut() = Some(starts);
+        }
+
+        Ref::map(self.line_index.borrow(), |cached| {
+            cached.as_ref().expect("just populated above")
+        })
     }
 
     /// Set the estimated total lines (from LSP or other source)
@@ -53,6 +146,135 @@ impl LineAnchorManager {
         }
     }
 
+    /// Count line terminators scanning backward from the end of the buffer,
+    /// stopping once `max_lines` have been found. Lets a tail/follow UI
+    /// check "does this file have at least N lines" without paying for the
+    /// forward scan `line_to_byte` needs to reach the final line of a huge
+    /// file.
+    pub fn line_count_from_end(&self, buffer: &Buffer, max_lines: usize) -> usize {
+        self.scan_terminators_from_end(buffer, max_lines).0
+    }
+
+    /// The byte range covering the last `n` lines of the buffer (clamped to
+    /// `0..buffer.len()` if the buffer has fewer than `n` lines).
+    pub fn last_n_lines_byte_range(&self, buffer: &Buffer, n: usize) -> Range<usize> {
+        if n == 0 {
+            return buffer.len()..buffer.len();
+        }
+        // If the buffer ends in a terminator, the text after it is an empty
+        // trailing "line" that doesn't count as one of the last `n` - crossing
+        // that final terminator just lands back at `buffer.len()`, so one
+        // extra terminator must be crossed to reach the start of the actual
+        // n-th line from the end.
+        let target = if self.ends_with_terminator(buffer) {
+            n.saturating_add(1)
+        } else {
+            n
+        };
+        let (found, start) = self.scan_terminators_from_end(buffer, target);
+        let start = if found >= target { start } else { 0 };
+        start..buffer.len()
+    }
+
+    /// Whether the buffer's last byte is a line terminator (`\n` or `\r`).
+    fn ends_with_terminator(&self, buffer: &Buffer) -> bool {
+        buffer.len() > 0
+            && matches!(
+                buffer.slice(buffer.len() - 1..buffer.len()).as_bytes()[0],
+                b'\n' | b'\r'
+            )
+    }
+
+    /// Scan backward from `buffer.len()` in fixed-size blocks, counting
+    /// terminators with `memrchr` rather than re-walking the whole file,
+    /// stopping once `n` are found. Each backward step carries one byte of
+    /// lookback past the block boundary so a `\r\n` pair split exactly on
+    /// that boundary is classified correctly instead of being counted twice.
+    ///
+    /// Returns `(terminators found, byte offset of the n-th line from the
+    /// end)`; the offset is only meaningful when at least `n` were found.
+    fn scan_terminators_from_end(&self, buffer: &Buffer, n: usize) -> (usize, usize) {
+        let mut count = 0;
+        let mut upper = buffer.len();
+        let mut line_start = 0;
+
+        while upper > 0 && count < n {
+            let block_start = upper.saturating_sub(TAIL_BLOCK_SIZE);
+            let read_start = block_start.saturating_sub(1);
+            let bytes = buffer.slice(read_start..upper).as_bytes();
+            let mut scan_end = bytes.len();
+            let mut next_upper = block_start;
+
+            while count < n {
+                let Some((pos, width)) = self.find_terminator_backward(&bytes[..scan_end]) else {
+                    break;
+                };
+                count += 1;
+                line_start = read_start + pos + width;
+                if count >= n {
+                    break;
+                }
+                if pos == 0 {
+                    // The terminator touches the lookback byte itself, so
+                    // the next block must not rescan it - nor the `\r` of a
+                    // `\r\n` pair whose `\n` is that lookback byte.
+                    next_upper = if width == 1
+                        && bytes[0] == b'\n'
+                        && read_start > 0
+                        && buffer.slice(read_start - 1..read_start).as_bytes()[0] == b'\r'
+                    {
+                        read_start - 1
+                    } else {
+                        read_start
+                    };
+                    break;
+                }
+                scan_end = pos;
+            }
+
+            upper = next_upper;
+        }
+
+        (count, line_start)
+    }
+
+    /// Count terminators in a raw byte slice, honoring `self.line_ending`.
+    fn count_terminators(&self, mut bytes: &[u8]) -> usize {
+        let mut count = 0;
+        while let Some((pos, width)) = self.find_terminator_forward(bytes) {
+            count += 1;
+            bytes = &bytes[pos + width..];
+        }
+        count
+    }
+
+    /// Incrementally extend anchors after content has been appended to the
+    /// end of the buffer (e.g. a log file being tailed), without rescanning
+    /// from the start: counts lines only in the newly appended bytes and
+    /// drops a fresh anchor at the start of the appended region.
+    pub fn on_append(&mut self, buffer: &Buffer, new_size: usize, markers: &mut MarkerList) {
+        let old_size = self.file_size;
+        if new_size <= old_size {
+            return;
+        }
+
+        let appended = buffer.slice(old_size..new_size).as_bytes();
+        let new_lines = self.count_terminators(appended);
+
+        let total_before = self.estimated_total_lines.unwrap_or(0);
+        self.estimated_total_lines = Some(total_before + new_lines);
+        self.file_size = new_size;
+        // The cached line index knows nothing of the appended lines.
+        self.line_index.borrow_mut().take();
+
+        // The byte offset (`old_size`) is exact, but `total_before` is
+        // itself a heuristic (`file_size / avg_line_length`, or whatever an
+        // LSP last told us via `set_estimated_total_lines`) for any manager
+        // that hasn't exactly counted every line from the start - so the
+        // anchor's line number can't be labelled `Exact` either.
+        markers.create_line_anchor(old_size, new_size, total_before, AnchorConfidence::Estimated);
+    }
+
     /// Convert line number to byte offset
     ///
     /// Creates anchors as needed using estimation for huge jumps.
@@ -68,39 +290,12 @@ impl LineAnchorManager {
             return start;
         }
 
-        // For small files (< threshold), always scan from the beginning to get exact positions
-        // This avoids estimation errors on small files
+        // For small files (< threshold), use the fully-materialized line
+        // index instead of rescanning from line 0 on every call: the first
+        // lookup builds it once, every call after is an O(1) table access.
         if self.file_size < self.large_file_threshold {
-            // Check if we have an anchor at line 0
-            if let Some((anchor_id, anchor_start, _, anchor_line)) =
-                markers.nearest_line_anchor_before_line(line_num)
-            {
-                if anchor_line == 0 {
-                    // Scan from line 0
-                    return self.scan_forward_n_lines(
-                        buffer,
-                        markers,
-                        anchor_id,
-                        anchor_start,
-                        anchor_line,
-                        line_num,
-                    );
-                }
-            }
-
-            // No anchor at line 0, create one and scan from there
-            let line0_end = self.scan_to_next_newline(buffer, 0);
-            markers.create_line_anchor(
-                0,
-                line0_end,
-                0,
-                crate::marker_tree::AnchorConfidence::Exact,
-            );
-            let anchor_id = markers
-                .nearest_line_anchor_before_line(line_num + 1)
-                .unwrap()
-                .0;
-            return self.scan_forward_n_lines(buffer, markers, anchor_id, 0, 0, line_num);
+            let index = self.ensure_line_index(buffer);
+            return index.get(line_num).copied().unwrap_or(buffer.len());
         }
 
         // Find nearest anchor before this line
@@ -135,6 +330,16 @@ impl LineAnchorManager {
         buffer: &Buffer,
         markers: &mut MarkerList,
     ) -> usize {
+        // For small files, the materialized line index turns this into a
+        // binary search for the last line start at or before `byte_offset`.
+        if self.file_size < self.large_file_threshold {
+            let index = self.ensure_line_index(buffer);
+            return match index.binary_search(&byte_offset) {
+                Ok(line) => line,
+                Err(insertion_point) => insertion_point.saturating_sub(1),
+            };
+        }
+
         // Check if there's an anchor containing this byte
         let anchors = markers.query_line_anchors(byte_offset, byte_offset + 1);
         if let Some((_, _, _, line_num)) = anchors.first() {
@@ -214,21 +419,78 @@ impl LineAnchorManager {
         line_start
     }
 
+    /// Find the last terminator in `bytes`, honoring `self.line_ending`.
+    /// Returns `(terminator_start, terminator_width)` relative to `bytes`.
+    fn find_terminator_backward(&self, bytes: &[u8]) -> Option<(usize, usize)> {
+        match self.line_ending {
+            LineEnding::Cr => memrchr(b'\r', bytes).map(|pos| (pos, 1)),
+            LineEnding::Lf | LineEnding::CrLf => memrchr(b'\n', bytes).map(|pos| {
+                if pos > 0 && bytes[pos - 1] == b'\r' {
+                    (pos - 1, 2)
+                } else {
+                    (pos, 1)
+                }
+            }),
+            LineEnding::Mixed => memrchr2(b'\n', b'\r', bytes).map(|pos| match bytes[pos] {
+                b'\r' => (pos, 1),
+                _ if pos > 0 && bytes[pos - 1] == b'\r' => (pos - 1, 2),
+                _ => (pos, 1),
+            }),
+        }
+    }
+
+    /// Find the first terminator in `bytes`, honoring `self.line_ending`.
+    /// Returns `(terminator_start, terminator_width)` relative to `bytes`.
+    fn find_terminator_forward(&self, bytes: &[u8]) -> Option<(usize, usize)> {
+        match self.line_ending {
+            LineEnding::Cr => memchr(b'\r', bytes).map(|pos| (pos, 1)),
+            LineEnding::Lf | LineEnding::CrLf => memchr(b'\n', bytes).map(|pos| {
+                if pos > 0 && bytes[pos - 1] == b'\r' {
+                    (pos - 1, 2)
+                } else {
+                    (pos, 1)
+                }
+            }),
+            LineEnding::Mixed => memchr2(b'\n', b'\r', bytes).map(|pos| match bytes[pos] {
+                b'\n' => (pos, 1),
+                _ if pos + 1 < bytes.len() && bytes[pos + 1] == b'\n' => (pos, 2),
+                _ => (pos, 1),
+            }),
+        }
+    }
+
+    /// The width in bytes of the terminator starting at `pos` (1 for `\n`/
+    /// lone `\r`, 2 for `\r\n`, 0 if `pos` is at or past the end of the
+    /// buffer - i.e. a final line with no trailing terminator at all).
+    fn terminator_width_at(&self, buffer: &Buffer, pos: usize) -> usize {
+        if pos >= buffer.len() {
+            return 0;
+        }
+        match buffer.slice(pos..pos + 1).as_bytes()[0] {
+            b'\r' if pos + 1 < buffer.len() && buffer.slice(pos + 1..pos + 2).as_bytes()[0] == b'\n' => 2,
+            b'\r' | b'\n' => 1,
+            _ => 0,
+        }
+    }
+
     /// Scan backward to find the previous newline (start of current line)
     fn scan_to_prev_newline(&self, buffer: &Buffer, from_byte: usize) -> usize {
         if from_byte == 0 {
             return 0;
         }
 
-        // Scan backward up to avg_line_length bytes
+        // Scan backward up to avg_line_length bytes. Search the raw byte
+        // slice with memrchr (SIMD on AVX2/SSE2) rather than `str::rfind`,
+        // which has to validate UTF-8 boundaries as it goes.
         let search_start = from_byte.saturating_sub(self.avg_line_length);
-        let slice = buffer.slice(search_start..from_byte);
+        let bytes = buffer.slice(search_start..from_byte).as_bytes();
 
-        // Find last newline in slice
-        if let Some(pos) = slice.rfind('\n') {
-            search_start + pos + 1 // Position after the newline
+        // Find last terminator in slice, and skip past its full width
+        // (1 byte for `\n`/`\r`, 2 for `\r\n`) to land on the next line's start.
+        if let Some((pos, width)) = self.find_terminator_backward(bytes) {
+            search_start + pos + width
         } else {
-            // No newline found, this might be near start of file
+            // No terminator found, this might be near start of file
             if search_start == 0 {
                 0
             } else {
@@ -238,17 +500,20 @@ impl LineAnchorManager {
         }
     }
 
-    /// Scan forward to find the next newline (end of current line)
+    /// Scan forward to find the next newline (end of current line). Returns
+    /// the byte offset where the terminator *starts* - callers that need to
+    /// step past it to the next line should add `terminator_width_at`.
     fn scan_to_next_newline(&self, buffer: &Buffer, from_byte: usize) -> usize {
-        // Scan forward up to avg_line_length bytes
+        // Scan forward up to avg_line_length bytes. memchr vectorizes the
+        // search instead of the byte-by-byte walk `str::find` does.
         let search_end = (from_byte + self.avg_line_length).min(buffer.len());
-        let slice = buffer.slice(from_byte..search_end);
+        let bytes = buffer.slice(from_byte..search_end).as_bytes();
 
-        // Find first newline in slice
-        if let Some(pos) = slice.find('\n') {
+        // Find first terminator in slice
+        if let Some((pos, _width)) = self.find_terminator_forward(bytes) {
             from_byte + pos
         } else {
-            // No newline found
+            // No terminator found
             if search_end == buffer.len() {
                 buffer.len()
             } else {
@@ -280,7 +545,7 @@ impl LineAnchorManager {
                 return buffer.len();
             }
 
-            current_byte = line_end + 1; // Move past the newline
+            current_byte = line_end + self.terminator_width_at(buffer, line_end);
             current_line += 1;
 
             // Create relative anchor every ~10 lines for better distribution
@@ -314,12 +579,15 @@ impl LineAnchorManager {
         while current_byte < target_byte {
             // Find next newline
             let line_end = self.scan_to_next_newline(buffer, current_byte);
-            if line_end >= target_byte {
-                // Target is in this line
+            let terminator_width = self.terminator_width_at(buffer, line_end);
+            if target_byte < line_end + terminator_width {
+                // Target is in this line's content, or inside a multi-byte
+                // terminator (e.g. the `\n` half of a `\r\n` pair) - either
+                // way it belongs to the current line, not the next.
                 break;
             }
 
-            current_byte = line_end + 1;
+            current_byte = line_end + terminator_width;
             current_line += 1;
 
             // Create relative anchor periodically
@@ -347,7 +615,7 @@ mod tests {
         let buffer = Buffer::from_str_test("line1\nline2\nline3\n");
         let mut markers = MarkerList::new();
         let manager = LineAnchorManager::new(
-            buffer.len(),
+            &buffer,
             crate::config::LARGE_FILE_THRESHOLD_BYTES as usize,
         );
 
@@ -372,7 +640,7 @@ mod tests {
         let buffer = Buffer::from_str_test(&text);
         let mut markers = MarkerList::new();
         let manager = LineAnchorManager::new(
-            buffer.len(),
+            &buffer,
             crate::config::LARGE_FILE_THRESHOLD_BYTES as usize,
         );
 
@@ -403,7 +671,7 @@ mod tests {
         let buffer = Buffer::from_str_test(&text);
         let mut markers = MarkerList::new();
         let manager = LineAnchorManager::new(
-            buffer.len(),
+            &buffer,
             crate::config::LARGE_FILE_THRESHOLD_BYTES as usize,
         );
 
@@ -430,7 +698,7 @@ mod tests {
         let mut markers = MarkerList::new();
 
         // Use a threshold smaller than the buffer size
-        let manager = LineAnchorManager::new(buffer.len(), 1000);
+        let manager = LineAnchorManager::new(&buffer, 1000);
 
         // Jump to line 50 - should use estimation since file is larger than threshold
         let byte = manager.line_to_byte(50, &buffer, &mut markers);
@@ -447,4 +715,187 @@ mod tests {
             diff
         );
     }
+
+    #[test]
+    fn test_detects_crlf_line_ending() {
+        let buffer = Buffer::from_str_test("line1\r\nline2\r\nline3\r\n");
+        assert_eq!(detect_line_ending(&buffer), LineEnding::CrLf);
+    }
+
+    #[test]
+    fn test_detects_lone_cr_line_ending() {
+        let buffer = Buffer::from_str_test("line1\rline2\rline3\r");
+        assert_eq!(detect_line_ending(&buffer), LineEnding::Cr);
+    }
+
+    #[test]
+    fn test_detects_mixed_line_ending() {
+        let buffer = Buffer::from_str_test("line1\r\nline2\nline3\r\n");
+        assert_eq!(detect_line_ending(&buffer), LineEnding::Mixed);
+    }
+
+    #[test]
+    fn test_crlf_scan_advances_past_full_terminator() {
+        let buffer = Buffer::from_str_test("line1\r\nline2\r\nline3\r\n");
+        let mut markers = MarkerList::new();
+        let manager = LineAnchorManager::new(
+            &buffer,
+            crate::config::LARGE_FILE_THRESHOLD_BYTES as usize,
+        );
+
+        // "line1\r\n" is 7 bytes, so line 1 starts at byte 7, not 6.
+        let byte = manager.line_to_byte(1, &buffer, &mut markers);
+        assert_eq!(byte, 7);
+    }
+
+    #[test]
+    fn test_byte_to_line_does_not_split_crlf_pair() {
+        let buffer = Buffer::from_str_test("line1\r\nline2\r\nline3\r\n");
+        let mut markers = MarkerList::new();
+        let manager = LineAnchorManager::new(
+            &buffer,
+            crate::config::LARGE_FILE_THRESHOLD_BYTES as usize,
+        );
+
+        // Byte 5 is the '\r' and byte 6 is the '\n' of line1's terminator;
+        // both must still resolve to line 0, not line 1.
+        assert_eq!(manager.byte_to_line(5, &buffer, &mut markers), 0);
+        assert_eq!(manager.byte_to_line(6, &buffer, &mut markers), 0);
+        assert_eq!(manager.byte_to_line(7, &buffer, &mut markers), 1);
+    }
+
+    #[test]
+    fn test_line_count_from_end_counts_terminators() {
+        let buffer = Buffer::from_str_test("a\nb\nc\nd\n");
+        let manager = LineAnchorManager::new(
+            &buffer,
+            crate::config::LARGE_FILE_THRESHOLD_BYTES as usize,
+        );
+
+        assert_eq!(manager.line_count_from_end(&buffer, 100), 4);
+        // Stops early once the requested count is reached.
+        assert_eq!(manager.line_count_from_end(&buffer, 2), 2);
+    }
+
+    #[test]
+    fn test_last_n_lines_byte_range() {
+        let buffer = Buffer::from_str_test("a\nb\nc\nd\n");
+        let manager = LineAnchorManager::new(
+            &buffer,
+            crate::config::LARGE_FILE_THRESHOLD_BYTES as usize,
+        );
+
+        // Last 2 lines are "c\n" and "d\n", starting right after "a\nb\n" (4 bytes).
+        let range = manager.last_n_lines_byte_range(&buffer, 2);
+        assert_eq!(range, 4..buffer.len());
+        assert_eq!(buffer.slice(range).as_bytes(), b"c\nd\n");
+    }
+
+    #[test]
+    fn test_last_n_lines_byte_range_clamps_when_fewer_lines_exist() {
+        let buffer = Buffer::from_str_test("a\nb\n");
+        let manager = LineAnchorManager::new(
+            &buffer,
+            crate::config::LARGE_FILE_THRESHOLD_BYTES as usize,
+        );
+
+        let range = manager.last_n_lines_byte_range(&buffer, 100);
+        assert_eq!(range, 0..buffer.len());
+    }
+
+    #[test]
+    fn test_line_count_from_end_handles_crlf_split_across_block_boundary() {
+        // Size the text so the first backward block starts exactly between
+        // the '\r' and '\n' of the first CRLF pair: everything from that
+        // '\n' to the end is TAIL_BLOCK_SIZE bytes.
+        let padding = "x".repeat(TAIL_BLOCK_SIZE - 6);
+        let text = format!("a\r\n{}\r\nc\r\n", padding);
+        let buffer = Buffer::from_str_test(&text);
+        let manager = LineAnchorManager::new(
+            &buffer,
+            crate::config::LARGE_FILE_THRESHOLD_BYTES as usize,
+        );
+
+        // Exactly 3 terminators total, regardless of where the 4KB block
+        // boundary happens to land.
+        assert_eq!(manager.line_count_from_end(&buffer, 100), 3);
+    }
+
+    #[test]
+    fn test_on_append_extends_estimated_total_lines() {
+        let old_buffer = Buffer::from_str_test("a\nb\n");
+        let full_buffer = Buffer::from_str_test("a\nb\nc\nd\n");
+        let mut markers = MarkerList::new();
+        let mut manager = LineAnchorManager::new(
+            &old_buffer,
+            crate::config::LARGE_FILE_THRESHOLD_BYTES as usize,
+        );
+        manager.set_estimated_total_lines(2);
+
+        manager.on_append(&full_buffer, full_buffer.len(), &mut markers);
+
+        assert_eq!(manager.estimated_total_lines, Some(4));
+
+        let anchors = markers.query_line_anchors(0, full_buffer.len());
+        assert!(
+            anchors
+                .iter()
+                .any(|(_, start, _, line)| *start == 4 && *line == 2),
+            "expected a fresh anchor at the appended region's start"
+        );
+    }
+
+    #[test]
+    fn test_line_index_built_lazily_and_cached() {
+        let buffer = Buffer::from_str_test("a\nbb\nccc\n");
+        let mut markers = MarkerList::new();
+        let manager = LineAnchorManager::new(
+            &buffer,
+            crate::config::LARGE_FILE_THRESHOLD_BYTES as usize,
+        );
+
+        assert!(manager.line_index.borrow().is_none());
+
+        assert_eq!(manager.line_to_byte(0, &buffer, &mut markers), 0);
+        assert_eq!(manager.line_to_byte(1, &buffer, &mut markers), 2);
+        assert_eq!(manager.line_to_byte(2, &buffer, &mut markers), 5);
+        // Past the last line: no such line exists.
+        assert_eq!(manager.line_to_byte(3, &buffer, &mut markers), buffer.len());
+
+        assert!(manager.line_index.borrow().is_some());
+    }
+
+    #[test]
+    fn test_byte_to_line_uses_line_index_for_small_files() {
+        let buffer = Buffer::from_str_test("a\nbb\nccc\n");
+        let mut markers = MarkerList::new();
+        let manager = LineAnchorManager::new(
+            &buffer,
+            crate::config::LARGE_FILE_THRESHOLD_BYTES as usize,
+        );
+
+        assert_eq!(manager.byte_to_line(0, &buffer, &mut markers), 0);
+        assert_eq!(manager.byte_to_line(1, &buffer, &mut markers), 0);
+        assert_eq!(manager.byte_to_line(2, &buffer, &mut markers), 1);
+        assert_eq!(manager.byte_to_line(5, &buffer, &mut markers), 2);
+    }
+
+    #[test]
+    fn test_update_file_size_invalidates_line_index() {
+        let buffer = Buffer::from_str_test("a\nbb\n");
+        let mut markers = MarkerList::new();
+        let mut manager = LineAnchorManager::new(
+            &buffer,
+            crate::config::LARGE_FILE_THRESHOLD_BYTES as usize,
+        );
+
+        let _ = manager.line_to_byte(1, &buffer, &mut markers);
+        assert!(manager.line_index.borrow().is_some());
+
+        manager.update_file_size(buffer.len());
+        assert!(
+            manager.line_index.borrow().is_none(),
+            "edits must invalidate the cached line index"
+        );
+    }
 }
@@ -5,16 +5,34 @@
 use crate::buffer::Buffer;
 use crate::overlay::OverlayFace;
 use crate::state::EditorState;
-use lsp_types::{Diagnostic, DiagnosticSeverity};
+use lsp_types::{Diagnostic, DiagnosticSeverity, DiagnosticTag, Url};
 use ratatui::style::Color;
 use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
 use std::hash::{Hash, Hasher};
 use std::ops::Range;
 use std::sync::Mutex;
 
-/// Cache for diagnostic hash to avoid redundant updates
-/// Using a global static with Mutex for simplicity - could be moved to EditorState later
-static DIAGNOSTIC_CACHE: Mutex<Option<u64>> = Mutex::new(None);
+/// Per-document cache of the last-seen diagnostic hash, keyed by buffer URI.
+///
+/// Using a global static with Mutex for simplicity - could be moved to EditorState later.
+/// Keying by document identity (rather than a single shared slot) is what lets
+/// an editor with several open buffers, each backed by its own language
+/// server, avoid one buffer's publish clobbering another's cached hash.
+static DIAGNOSTIC_CACHE: Mutex<Option<HashMap<Url, u64>>> = Mutex::new(None);
+
+/// Drop the cached hash for a document, e.g. when it's closed.
+///
+/// Without this, closing and reopening a buffer under the same URI could
+/// see its stale cached hash match the first publish for the new buffer
+/// and incorrectly skip applying its diagnostics.
+pub fn evict_diagnostic_cache(uri: &Url) {
+    if let Ok(mut cache) = DIAGNOSTIC_CACHE.lock() {
+        if let Some(map) = cache.as_mut() {
+            map.remove(uri);
+        }
+    }
+}
 
 /// Compute a hash for a slice of diagnostics
 /// This hash is used to quickly detect if diagnostics have changed
@@ -50,27 +68,88 @@ fn compute_diagnostic_hash(diagnostics: &[Diagnostic]) -> u64 {
         if let Some(source) = &diag.source {
             source.hash(&mut hasher);
         }
+
+        // Hash tags so a tag-only change (e.g. a diagnostic becoming deprecated)
+        // invalidates the cache even though range/severity/message are unchanged
+        if let Some(tags) = &diag.tags {
+            let mut tag_values: Vec<i32> = tags.iter().map(|t| t.0).collect();
+            tag_values.sort_unstable();
+            tag_values.hash(&mut hasher);
+        }
+
+        // Hash the code and its documentation link so e.g. E0433 -> E0434
+        // with the same message still invalidates the margin/hover cache.
+        match &diag.code {
+            Some(lsp_types::NumberOrString::Number(n)) => n.hash(&mut hasher),
+            Some(lsp_types::NumberOrString::String(s)) => s.hash(&mut hasher),
+            None => {}
+        }
+        if let Some(code_description) = &diag.code_description {
+            code_description.href.as_str().hash(&mut hasher);
+        }
+
+        // Hash related-information locations/messages so a change limited to
+        // those spans still invalidates the cache. `apply_diagnostics_to_state`
+        // keeps the overlays (`-rel` ones included) of a diagnostic whose
+        // primary id is already present, so such a change does not rebuild them.
+        if let Some(related) = &diag.related_information {
+            for info in related {
+                info.location.uri.as_str().hash(&mut hasher);
+                info.location.range.start.line.hash(&mut hasher);
+                info.location.range.start.character.hash(&mut hasher);
+                info.location.range.end.line.hash(&mut hasher);
+                info.location.range.end.character.hash(&mut hasher);
+                info.message.hash(&mut hasher);
+            }
+        }
     }
 
     hasher.finish()
 }
 
+/// Configuration for how diagnostics are applied to editor state
+///
+/// Currently only controls which diagnostic sources are treated as
+/// "persistent" (see [`apply_diagnostics_to_state`]), but this is the
+/// natural place to grow future diagnostic-display knobs.
+#[derive(Debug, Clone, Default)]
+pub struct DiagnosticsConfig {
+    /// `diagnostic.source` values (e.g. `"rust-analyzer"`, `"eslint"`) that
+    /// resend their full diagnostic set on every publish even when nothing
+    /// changed. For these sources, an existing overlay's marker-anchored
+    /// range is kept across publishes instead of being re-derived from the
+    /// incoming (possibly stale) LSP position.
+    pub persistent_sources: Vec<String>,
+}
+
+impl DiagnosticsConfig {
+    fn is_persistent(&self, source: Option<&str>) -> bool {
+        source.is_some_and(|s| self.persistent_sources.iter().any(|p| p == s))
+    }
+}
+
 /// Apply LSP diagnostics to editor state with hash-based caching
 ///
 /// This is the recommended entry point that skips redundant work when diagnostics haven't changed.
 /// On a typical keystroke, diagnostics don't change, so this returns immediately.
+///
+/// `uri` identifies which open document these diagnostics belong to, so the
+/// fast-path short-circuit is per-buffer: a publish for one document can never
+/// shadow or be shadowed by the cached hash of another.
 pub fn apply_diagnostics_to_state_cached(
     state: &mut EditorState,
+    uri: &Url,
     diagnostics: &[Diagnostic],
     theme: &crate::theme::Theme,
+    config: &DiagnosticsConfig,
 ) {
     // Compute hash of incoming diagnostics
     let new_hash = compute_diagnostic_hash(diagnostics);
 
-    // Check if this is the same as last time
+    // Check if this is the same as last time for this document
     if let Ok(cache) = DIAGNOSTIC_CACHE.lock() {
-        if let Some(cached_hash) = *cache {
-            if cached_hash == new_hash {
+        if let Some(cached_hash) = cache.as_ref().and_then(|map| map.get(uri)) {
+            if *cached_hash == new_hash {
                 // Diagnostics haven't changed, skip all work
                 return;
             }
@@ -78,21 +157,36 @@ pub fn apply_diagnostics_to_state_cached(
     }
 
     // Diagnostics have changed, do the expensive update
-    apply_diagnostics_to_state(state, diagnostics, theme);
+    apply_diagnostics_to_state(state, diagnostics, theme, config);
 
     // Update cache
     if let Ok(mut cache) = DIAGNOSTIC_CACHE.lock() {
-        *cache = Some(new_hash);
+        cache
+            .get_or_insert_with(HashMap::new)
+            .insert(uri.clone(), new_hash);
     }
 }
 
-/// Convert an LSP diagnostic to an overlay (range, face, priority)
+/// Highest priority a tag face can take, always rendered above any severity face
+const TAG_FACE_PRIORITY: i32 = 200;
+
+/// Priority for related-information overlays - below every severity face so a
+/// "first defined here" hint never outranks the diagnostic it belongs to.
+const RELATED_INFO_PRIORITY: i32 = 5;
+
+/// Convert an LSP diagnostic to one or more overlays (range, face, priority)
+///
+/// The first entry is the severity-based face, except that an `Unnecessary`
+/// tag replaces it with a faded face at a higher priority. A `Deprecated` tag
+/// appends a strikethrough entry with a higher priority so it layers on top
+/// and wins visually, per the existing priority-stacking rule used for
+/// overlapping overlays.
 /// Returns None if the diagnostic cannot be converted (invalid range, etc.)
 pub fn diagnostic_to_overlay(
     diagnostic: &Diagnostic,
     buffer: &Buffer,
     theme: &crate::theme::Theme,
-) -> Option<(Range<usize>, OverlayFace, i32)> {
+) -> Option<Vec<(Range<usize>, OverlayFace, i32)>> {
     // Convert LSP positions (line/character) to byte offsets
     // LSP uses 0-indexed lines and characters (UTF-16 code units)
     let start_line = diagnostic.range.start.line as usize;
@@ -104,37 +198,123 @@ pub fn diagnostic_to_overlay(
     // LSP uses UTF-16 code units for character offsets
     let start_byte = buffer.lsp_position_to_byte(start_line, start_char);
     let end_byte = buffer.lsp_position_to_byte(end_line, end_char);
+    let range = start_byte..end_byte;
 
-    // Determine overlay face based on diagnostic severity using theme colors
-    let (face, priority) = match diagnostic.severity {
-        Some(DiagnosticSeverity::ERROR) => (
-            OverlayFace::Background {
-                color: theme.diagnostic_error_bg,
-            },
-            100, // Highest priority
-        ),
-        Some(DiagnosticSeverity::WARNING) => (
-            OverlayFace::Background {
-                color: theme.diagnostic_warning_bg,
-            },
-            50, // Medium priority
-        ),
-        Some(DiagnosticSeverity::INFORMATION) => (
-            OverlayFace::Background {
-                color: theme.diagnostic_info_bg,
-            },
-            30, // Lower priority
-        ),
-        Some(DiagnosticSeverity::HINT) | None => (
-            OverlayFace::Background {
-                color: theme.diagnostic_hint_bg,
-            },
-            10, // Lowest priority
-        ),
-        _ => return None, // Unknown severity
+    let has_tag = |tag: DiagnosticTag| {
+        diagnostic
+            .tags
+            .as_ref()
+            .is_some_and(|tags| tags.contains(&tag))
+    };
+    let unnecessary = has_tag(DiagnosticTag::UNNECESSARY);
+    let deprecated = has_tag(DiagnosticTag::DEPRECATED);
+
+    // Determine the severity color and priority using theme colors, then
+    // render it as the theme's configured diagnostic style: a solid
+    // background (the original look), a plain underline, or a wavy
+    // "squiggly" underline like most LSP clients draw. Underline/squiggly
+    // avoid obscuring syntax highlighting and read better when diagnostics
+    // overlap. An "unnecessary" tag suppresses this face entirely in favor
+    // of the faded face below, regardless of which style is configured.
+    let (color, priority) = match diagnostic.severity {
+        Some(DiagnosticSeverity::ERROR) => (theme.diagnostic_error_bg, 100), // Highest priority
+        Some(DiagnosticSeverity::WARNING) => (theme.diagnostic_warning_bg, 50), // Medium priority
+        Some(DiagnosticSeverity::INFORMATION) => (theme.diagnostic_info_bg, 30), // Lower priority
+        Some(DiagnosticSeverity::HINT) | None => (theme.diagnostic_hint_bg, 10), // Lowest priority
+        _ => return None,                                                  // Unknown severity
     };
+    let severity_face = match theme.diagnostic_style {
+        crate::theme::DiagnosticStyle::Background => OverlayFace::Background { color },
+        crate::theme::DiagnosticStyle::Underline => OverlayFace::Underline {
+            color,
+            squiggly: false,
+            thickness: 1,
+        },
+        crate::theme::DiagnosticStyle::Squiggly => OverlayFace::Underline {
+            color,
+            squiggly: true,
+            thickness: 1,
+        },
+    };
+
+    let mut overlays = Vec::with_capacity(2);
+    if unnecessary {
+        overlays.push((
+            range.clone(),
+            OverlayFace::Faded { alpha: 0.5 },
+            TAG_FACE_PRIORITY,
+        ));
+    } else {
+        overlays.push((range.clone(), severity_face, priority));
+    }
+    if deprecated {
+        overlays.push((range, OverlayFace::Strikethrough, TAG_FACE_PRIORITY));
+    }
+
+    Some(overlays)
+}
 
-    Some((start_byte..end_byte, face, priority))
+/// Strip a `-tagN` or `-relN` layer suffix (see [`diagnostic_to_overlay`] and
+/// [`related_information_overlay_id`]) off an overlay id, so a diagnostic's
+/// primary overlay and its tag/related layers are treated as one unit when
+/// deciding what's still present across a publish.
+fn overlay_base_id(id: &str) -> &str {
+    for marker in ["-tag", "-rel"] {
+        if let Some(idx) = id.rfind(marker) {
+            let (base, suffix) = id.split_at(idx);
+            let digits = &suffix[marker.len()..];
+            if suffix.len() > marker.len() && digits.chars().all(|c| c.is_ascii_digit()) {
+                return base;
+            }
+        }
+    }
+    id
+}
+
+/// Derive a related-information overlay's id from its parent diagnostic's id.
+///
+/// Giving it the `{parent_id}-rel{n}` shape both lets [`overlay_base_id`]
+/// remove it together with its parent, and exposes the parent→related
+/// linkage: stripping the suffix recovers the id of the primary overlay a
+/// "go to related" command would navigate back to.
+fn related_information_overlay_id(parent_id: &str, index: usize) -> String {
+    format!("{}-rel{}", parent_id, index)
+}
+
+/// Format a diagnostic's message for display, prefixing the error code when
+/// present (e.g. `[E0433] cannot find value ...`) the way rustc/most LSP
+/// clients surface it, so the code is visible in the margin/hover without a
+/// separate lookup.
+fn format_diagnostic_message(diagnostic: &Diagnostic) -> String {
+    match &diagnostic.code {
+        Some(lsp_types::NumberOrString::Number(n)) => format!("[{}] {}", n, diagnostic.message),
+        Some(lsp_types::NumberOrString::String(s)) => format!("[{}] {}", s, diagnostic.message),
+        None => diagnostic.message.clone(),
+    }
+}
+
+/// Rank a severity from most (0) to least (3) severe, for picking the
+/// "worst" severity among several diagnostics on the same line.
+fn severity_rank(severity: Option<DiagnosticSeverity>) -> u8 {
+    match severity {
+        Some(DiagnosticSeverity::ERROR) => 0,
+        Some(DiagnosticSeverity::WARNING) => 1,
+        Some(DiagnosticSeverity::INFORMATION) => 2,
+        Some(DiagnosticSeverity::HINT) | None => 3,
+        _ => 3,
+    }
+}
+
+/// Margin indicator color for a severity, matching the colors most editors
+/// use for their gutter dots (red/yellow/blue/gray).
+fn severity_indicator_color(severity: Option<DiagnosticSeverity>) -> Color {
+    match severity {
+        Some(DiagnosticSeverity::ERROR) => Color::Red,
+        Some(DiagnosticSeverity::WARNING) => Color::Yellow,
+        Some(DiagnosticSeverity::INFORMATION) => Color::Blue,
+        Some(DiagnosticSeverity::HINT) | None => Color::Gray,
+        _ => Color::Gray,
+    }
 }
 
 /// Create a stable ID for a diagnostic based on its content
@@ -151,6 +331,40 @@ fn diagnostic_id(diagnostic: &Diagnostic) -> String {
     format!("lsp-diagnostic-L{}C{}-{}", line, col, msg_hash)
 }
 
+/// Create a position-independent ID for a diagnostic from a "persistent" source
+/// (see [`DiagnosticsConfig::persistent_sources`]).
+///
+/// Unlike [`diagnostic_id`], this ignores the incoming range entirely and is
+/// keyed on source + code + message, so a diagnostic that's resent at a
+/// slightly different byte position (because the server recomputed the whole
+/// set while the user kept typing) still maps to the *same* overlay, which
+/// keeps that overlay's already-drifted, marker-anchored range intact rather
+/// than snapping it back to the stale position the server just reported.
+fn diagnostic_persistent_id(diagnostic: &Diagnostic) -> String {
+    let source = diagnostic.source.as_deref().unwrap_or("");
+    let code = match &diagnostic.code {
+        Some(lsp_types::NumberOrString::Number(n)) => n.to_string(),
+        Some(lsp_types::NumberOrString::String(s)) => s.clone(),
+        None => String::new(),
+    };
+
+    let mut hasher = DefaultHasher::new();
+    diagnostic.message.hash(&mut hasher);
+    let msg_hash = hasher.finish();
+
+    format!("lsp-diagnostic-persist-{}-{}-{:x}", source, code, msg_hash)
+}
+
+/// The ID under which a diagnostic's primary overlay is tracked, chosen
+/// according to whether its source is configured as persistent.
+fn overlay_id_for(diagnostic: &Diagnostic, config: &DiagnosticsConfig) -> String {
+    if config.is_persistent(diagnostic.source.as_deref()) {
+        diagnostic_persistent_id(diagnostic)
+    } else {
+        diagnostic_id(diagnostic)
+    }
+}
+
 /// Apply LSP diagnostics to editor state as overlays
 ///
 /// This function:
@@ -159,16 +373,27 @@ fn diagnostic_id(diagnostic: &Diagnostic) -> String {
 /// 3. Adds overlays for new diagnostics
 /// 4. Keeps overlays for unchanged diagnostics (incremental update)
 /// 5. Updates margin indicators
+///
+/// For diagnostics whose source is listed in `config.persistent_sources`, step 4
+/// also covers position drift: a re-published diagnostic that matches an
+/// existing overlay by [`diagnostic_persistent_id`] is left untouched rather
+/// than having its range recomputed from the (possibly now-stale) incoming
+/// LSP position, so the underline keeps following edits made since the anchor
+/// was first created.
 pub fn apply_diagnostics_to_state(
     state: &mut EditorState,
     diagnostics: &[Diagnostic],
     theme: &crate::theme::Theme,
+    config: &DiagnosticsConfig,
 ) {
     use crate::overlay::Overlay;
     use std::collections::HashSet;
 
     // Build set of incoming diagnostic IDs
-    let incoming_ids: HashSet<String> = diagnostics.iter().map(|d| diagnostic_id(d)).collect();
+    let incoming_ids: HashSet<String> = diagnostics
+        .iter()
+        .map(|d| overlay_id_for(d, config))
+        .collect();
 
     // Find existing diagnostic overlay IDs
     let existing_ids: Vec<String> = state
@@ -186,10 +411,12 @@ pub fn apply_diagnostics_to_state(
         })
         .collect();
 
-    // Remove overlays for diagnostics that are no longer present
+    // Remove overlays for diagnostics that are no longer present. An overlay's
+    // tag layers (see `diagnostic_to_overlay`) share the primary diagnostic's
+    // base id, so they're removed together with it.
     let mut removed_count = 0;
     for id in &existing_ids {
-        if !incoming_ids.contains(id) {
+        if !incoming_ids.contains(overlay_base_id(id)) {
             state.overlays.remove_by_id(id, &mut state.marker_list);
             removed_count += 1;
         }
@@ -201,47 +428,113 @@ pub fn apply_diagnostics_to_state(
     // Add new diagnostics (only those that don't already exist)
     let mut added_count = 0;
 
-    // Track unique lines with diagnostics to avoid duplicate margin markers
-    let mut diagnostic_lines = std::collections::HashSet::new();
+    // Track the worst severity seen per line, so the margin indicator color
+    // reflects the highest-severity diagnostic on that line rather than
+    // always assuming an error.
+    let mut diagnostic_lines: HashMap<usize, Option<DiagnosticSeverity>> = HashMap::new();
+    let mut track_line = |lines: &mut HashMap<usize, Option<DiagnosticSeverity>>,
+                           line: usize,
+                           severity: Option<DiagnosticSeverity>| {
+        lines
+            .entry(line)
+            .and_modify(|worst| {
+                if severity_rank(severity) < severity_rank(*worst) {
+                    *worst = severity;
+                }
+            })
+            .or_insert(severity);
+    };
 
     // Add new diagnostic overlays (skip if already exists)
     // The line anchor system creates anchors on-demand, so no pre-population needed
     for diagnostic in diagnostics {
-        let overlay_id = diagnostic_id(diagnostic);
+        let overlay_id = overlay_id_for(diagnostic, config);
 
         // Skip if this diagnostic already has an overlay
         if existing_id_set.contains(&overlay_id) {
             // Still track the line for margin indicators
             let line = diagnostic.range.start.line as usize;
-            diagnostic_lines.insert(line);
+            track_line(&mut diagnostic_lines, line, diagnostic.severity);
             continue;
         }
 
-        // This is a new diagnostic, create an overlay for it
-        if let Some((range, face, priority)) =
-            diagnostic_to_overlay(diagnostic, &state.buffer, theme)
-        {
-            let message = diagnostic.message.clone();
+        // This is a new diagnostic, create an overlay for it (and any tag layers)
+        if let Some(layers) = diagnostic_to_overlay(diagnostic, &state.buffer, theme) {
+            let message = format_diagnostic_message(diagnostic);
+
+            for (i, (range, face, priority)) in layers.into_iter().enumerate() {
+                // The primary (first) layer keeps the bare id; later tag layers get a
+                // suffix so they're removed together with their parent but don't
+                // collide with it.
+                let id = if i == 0 {
+                    overlay_id.clone()
+                } else {
+                    format!("{}-tag{}", overlay_id, i - 1)
+                };
+
+                let mut overlay = Overlay::with_id(&mut state.marker_list, range, face, id)
+                    .with_priority_value(priority)
+                    .with_message(message.clone());
+
+                // Carry the code's doc URL on the primary layer so a command
+                // can open it (e.g. rust-analyzer's `E0433` explainer page).
+                if i == 0 {
+                    if let Some(code_description) = &diagnostic.code_description {
+                        overlay = overlay.with_url(code_description.href.to_string());
+                    }
+                }
 
-            let overlay = Overlay::with_id(&mut state.marker_list, range, face, overlay_id)
-                .with_priority_value(priority)
-                .with_message(message);
+                state.overlays.add(overlay);
+            }
+
+            // Create a secondary, lower-priority overlay for each related
+            // information span (rustc-style "first defined here" sub-diagnostics).
+            // Their ids derive from the parent's so they're cleaned up together.
+            if let Some(related) = &diagnostic.related_information {
+                for (i, info) in related.iter().enumerate() {
+                    let start = info.location.range.start;
+                    let end = info.location.range.end;
+                    let rel_start = state
+                        .buffer
+                        .lsp_position_to_byte(start.line as usize, start.character as usize);
+                    let rel_end = state
+                        .buffer
+                        .lsp_position_to_byte(end.line as usize, end.character as usize);
+
+                    let rel_id = related_information_overlay_id(&overlay_id, i);
+                    let overlay = Overlay::with_id(
+                        &mut state.marker_list,
+                        rel_start..rel_end,
+                        OverlayFace::Background {
+                            color: theme.diagnostic_hint_bg,
+                        },
+                        rel_id,
+                    )
+                    .with_priority_value(RELATED_INFO_PRIORITY)
+                    .with_message(info.message.clone());
+
+                    state.overlays.add(overlay);
+                }
+            }
 
-            state.overlays.add(overlay);
             added_count += 1;
 
             // Track the line number for diagnostic indicator
             let line = diagnostic.range.start.line as usize;
-            diagnostic_lines.insert(line);
+            track_line(&mut diagnostic_lines, line, diagnostic.severity);
         }
     }
 
-    // Clear and rebuild diagnostic indicators (this is fast)
+    // Clear and rebuild diagnostic indicators (this is fast). The glyph/color
+    // is chosen by the highest severity present on that line, so a
+    // warning-only line gets a warning dot rather than always red.
     state.margins.clear_diagnostic_indicators();
-    for line in diagnostic_lines {
-        state
-            .margins
-            .set_diagnostic_indicator(line, "â—".to_string(), Color::Red);
+    for (line, severity) in diagnostic_lines {
+        state.margins.set_diagnostic_indicator(
+            line,
+            "●".to_string(),
+            severity_indicator_color(severity),
+        );
     }
 
     // Log incremental update stats
@@ -259,7 +552,7 @@ pub fn apply_diagnostics_to_state(
 mod tests {
     use super::*;
     use crate::buffer::Buffer;
-    use lsp_types::{Diagnostic, DiagnosticSeverity, Position, Range};
+    use lsp_types::{Diagnostic, DiagnosticSeverity, DiagnosticTag, Position, Range};
 
     #[test]
     fn test_lsp_position_to_byte() {
@@ -313,13 +606,15 @@ mod tests {
         let result = diagnostic_to_overlay(&diagnostic, &buffer, &theme);
         assert!(result.is_some());
 
-        let (range, face, priority) = result.unwrap();
-        assert_eq!(range, 0..5);
-        assert_eq!(priority, 100); // Error has highest priority
+        let layers = result.unwrap();
+        assert_eq!(layers.len(), 1); // No tags, so just the severity layer
+        let (range, face, priority) = &layers[0];
+        assert_eq!(*range, 0..5);
+        assert_eq!(*priority, 100); // Error has highest priority
 
         match face {
             OverlayFace::Background { color } => {
-                assert_eq!(color, theme.diagnostic_error_bg);
+                assert_eq!(*color, theme.diagnostic_error_bg);
             }
             _ => panic!("Expected Background face"),
         }
@@ -354,13 +649,15 @@ mod tests {
         let result = diagnostic_to_overlay(&diagnostic, &buffer, &theme);
         assert!(result.is_some());
 
-        let (range, face, priority) = result.unwrap();
-        assert_eq!(range, 6..11);
-        assert_eq!(priority, 50); // Warning has medium priority
+        let layers = result.unwrap();
+        assert_eq!(layers.len(), 1); // No tags, so just the severity layer
+        let (range, face, priority) = &layers[0];
+        assert_eq!(*range, 6..11);
+        assert_eq!(*priority, 50); // Warning has medium priority
 
         match face {
             OverlayFace::Background { color } => {
-                assert_eq!(color, theme.diagnostic_warning_bg);
+                assert_eq!(*color, theme.diagnostic_warning_bg);
             }
             _ => panic!("Expected Background face"),
         }
@@ -395,11 +692,240 @@ mod tests {
         let result = diagnostic_to_overlay(&diagnostic, &buffer, &theme);
         assert!(result.is_some());
 
-        let (range, _, _) = result.unwrap();
+        let layers = result.unwrap();
+        let (range, _, _) = &layers[0];
         // "line1\n" is 6 bytes, "li" is 2 bytes
         // start: line 0, char 3 = byte 3 ("e1")
         // end: line 1, char 2 = byte 8 ("ne")
         assert_eq!(range.start, 3);
         assert_eq!(range.end, 8);
     }
+
+    #[test]
+    fn test_diagnostic_to_overlay_unnecessary_tag_fades_and_suppresses_background() {
+        let buffer = Buffer::from_str_test("let _unused = 1;");
+
+        let diagnostic = Diagnostic {
+            range: Range {
+                start: Position {
+                    line: 0,
+                    character: 4,
+                },
+                end: Position {
+                    line: 0,
+                    character: 11,
+                },
+            },
+            severity: Some(DiagnosticSeverity::WARNING),
+            code: None,
+            code_description: None,
+            source: None,
+            message: "unused variable".to_string(),
+            related_information: None,
+            tags: Some(vec![DiagnosticTag::UNNECESSARY]),
+            data: None,
+        };
+
+        let theme = crate::theme::Theme::dark();
+        let layers = diagnostic_to_overlay(&diagnostic, &buffer, &theme).unwrap();
+
+        // The severity background is suppressed in favor of a faded face
+        assert_eq!(layers.len(), 1);
+        let (_, face, priority) = &layers[0];
+        assert_eq!(*priority, TAG_FACE_PRIORITY);
+        match face {
+            OverlayFace::Faded { .. } => {}
+            _ => panic!("Expected Faded face for UNNECESSARY tag"),
+        }
+    }
+
+    #[test]
+    fn test_diagnostic_to_overlay_deprecated_tag_layers_strikethrough() {
+        let buffer = Buffer::from_str_test("old_api();");
+
+        let diagnostic = Diagnostic {
+            range: Range {
+                start: Position {
+                    line: 0,
+                    character: 0,
+                },
+                end: Position {
+                    line: 0,
+                    character: 7,
+                },
+            },
+            severity: Some(DiagnosticSeverity::HINT),
+            code: None,
+            code_description: None,
+            source: None,
+            message: "deprecated".to_string(),
+            related_information: None,
+            tags: Some(vec![DiagnosticTag::DEPRECATED]),
+            data: None,
+        };
+
+        let theme = crate::theme::Theme::dark();
+        let layers = diagnostic_to_overlay(&diagnostic, &buffer, &theme).unwrap();
+
+        // The severity layer stays, with a strikethrough layered on top of it
+        assert_eq!(layers.len(), 2);
+        assert!(matches!(layers[0].1, OverlayFace::Background { .. }));
+        assert!(matches!(layers[1].1, OverlayFace::Strikethrough));
+        assert_eq!(layers[1].2, TAG_FACE_PRIORITY);
+    }
+
+    fn make_diagnostic(source: &str, message: &str, line: u32) -> Diagnostic {
+        Diagnostic {
+            range: Range {
+                start: Position { line, character: 0 },
+                end: Position {
+                    line,
+                    character: 5,
+                },
+            },
+            severity: Some(DiagnosticSeverity::ERROR),
+            code: None,
+            code_description: None,
+            source: Some(source.to_string()),
+            message: message.to_string(),
+            related_information: None,
+            tags: None,
+            data: None,
+        }
+    }
+
+    #[test]
+    fn test_diagnostic_persistent_id_ignores_position() {
+        let a = make_diagnostic("rust-analyzer", "cannot find value `x`", 3);
+        let b = make_diagnostic("rust-analyzer", "cannot find value `x`", 7);
+
+        // Same source/message but a different line - still the same identity
+        assert_eq!(diagnostic_persistent_id(&a), diagnostic_persistent_id(&b));
+
+        // The position-based id, by contrast, differs
+        assert_ne!(diagnostic_id(&a), diagnostic_id(&b));
+    }
+
+    #[test]
+    fn test_overlay_id_for_respects_persistent_sources_config() {
+        let diagnostic = make_diagnostic("rust-analyzer", "cannot find value `x`", 3);
+
+        let config = DiagnosticsConfig {
+            persistent_sources: vec!["rust-analyzer".to_string()],
+        };
+        assert_eq!(
+            overlay_id_for(&diagnostic, &config),
+            diagnostic_persistent_id(&diagnostic)
+        );
+
+        let config = DiagnosticsConfig::default();
+        assert_eq!(overlay_id_for(&diagnostic, &config), diagnostic_id(&diagnostic));
+    }
+
+    #[test]
+    fn test_overlay_base_id_strips_tag_suffix() {
+        assert_eq!(
+            overlay_base_id("lsp-diagnostic-L3C0-abcdefgh-tag0"),
+            "lsp-diagnostic-L3C0-abcdefgh"
+        );
+        assert_eq!(
+            overlay_base_id("lsp-diagnostic-L3C0-abcdefgh"),
+            "lsp-diagnostic-L3C0-abcdefgh"
+        );
+    }
+
+    #[test]
+    fn test_overlay_base_id_strips_rel_suffix() {
+        let parent = "lsp-diagnostic-L3C0-abcdefgh";
+        let rel_id = related_information_overlay_id(parent, 0);
+        assert_eq!(rel_id, "lsp-diagnostic-L3C0-abcdefgh-rel0");
+        assert_eq!(overlay_base_id(&rel_id), parent);
+    }
+
+    #[test]
+    fn test_diagnostic_to_overlay_squiggly_style() {
+        let buffer = Buffer::from_str_test("hello world");
+        let diagnostic = make_diagnostic("rustc", "unused import", 0);
+
+        let mut theme = crate::theme::Theme::dark();
+        theme.diagnostic_style = crate::theme::DiagnosticStyle::Squiggly;
+
+        let layers = diagnostic_to_overlay(&diagnostic, &buffer, &theme).unwrap();
+        match &layers[0].1 {
+            OverlayFace::Underline {
+                squiggly,
+                thickness,
+                ..
+            } => {
+                assert!(*squiggly);
+                assert_eq!(*thickness, 1);
+            }
+            _ => panic!("Expected a squiggly Underline face"),
+        }
+    }
+
+    #[test]
+    fn test_format_diagnostic_message_includes_code() {
+        let mut diagnostic = make_diagnostic("rustc", "cannot find value `x`", 0);
+        diagnostic.code = Some(lsp_types::NumberOrString::String("E0433".to_string()));
+        assert_eq!(
+            format_diagnostic_message(&diagnostic),
+            "[E0433] cannot find value `x`"
+        );
+
+        let diagnostic = make_diagnostic("eslint", "unused variable", 0);
+        assert_eq!(
+            format_diagnostic_message(&diagnostic),
+            "unused variable"
+        );
+    }
+
+    #[test]
+    fn test_severity_indicator_color_reflects_worst_severity() {
+        assert_eq!(
+            severity_indicator_color(Some(DiagnosticSeverity::ERROR)),
+            Color::Red
+        );
+        assert_eq!(
+            severity_indicator_color(Some(DiagnosticSeverity::WARNING)),
+            Color::Yellow
+        );
+        assert!(
+            severity_rank(Some(DiagnosticSeverity::ERROR))
+                < severity_rank(Some(DiagnosticSeverity::WARNING))
+        );
+    }
+
+    #[test]
+    fn test_diagnostic_cache_is_per_document() {
+        let uri_a: Url = "file:///a.rs".parse().unwrap();
+        let uri_b: Url = "file:///b.rs".parse().unwrap();
+
+        evict_diagnostic_cache(&uri_a);
+        evict_diagnostic_cache(&uri_b);
+
+        let diagnostics = vec![make_diagnostic("rustc", "mismatched types", 1)];
+        let hash = compute_diagnostic_hash(&diagnostics);
+
+        // Simulate buffer A having already published this hash.
+        {
+            let mut cache = DIAGNOSTIC_CACHE.lock().unwrap();
+            cache.get_or_insert_with(HashMap::new).insert(uri_a.clone(), hash);
+        }
+
+        // Buffer B publishing the exact same diagnostics must NOT be treated
+        // as a cache hit just because A already has that hash cached.
+        let cached_for_b = {
+            let cache = DIAGNOSTIC_CACHE.lock().unwrap();
+            cache.as_ref().and_then(|m| m.get(&uri_b)).copied()
+        };
+        assert_eq!(cached_for_b, None);
+
+        evict_diagnostic_cache(&uri_a);
+        let cached_for_a = {
+            let cache = DIAGNOSTIC_CACHE.lock().unwrap();
+            cache.as_ref().and_then(|m| m.get(&uri_a)).copied()
+        };
+        assert_eq!(cached_for_a, None, "eviction should clear A's entry");
+    }
 }
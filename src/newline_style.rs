@@ -0,0 +1,208 @@
+/// Newline style detection and preservation
+///
+/// Helpers for the boundary between a file on disk and a buffer whose lines
+/// are normalized to `\n`: what terminator a file was opened with, how to
+/// normalize its content to `\n`, and what terminator to reattach when it is
+/// written back out. Nothing here normalizes a buffer on open or rewrites it
+/// on save by itself; callers pass the content, the style and the detected
+/// terminator explicitly.
+use std::ops::AddAssign;
+
+/// How a buffer's line endings should be handled on open and save.
+///
+/// Shaped like the other per-language knobs (`use_tabs`, `tab_size`), but no
+/// language config carries one of these; callers choose the style they pass
+/// to [`apply_terminator`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NewlineStyle {
+    /// Detect the dominant terminator on open and preserve it on save.
+    #[default]
+    Auto,
+    /// Always use `\n`.
+    Unix,
+    /// Always use `\r\n`.
+    Windows,
+    /// Use the host platform's native convention (`\n` on Unix, `\r\n` on Windows).
+    Native,
+}
+
+impl NewlineStyle {
+    /// The terminator this style resolves to for a file detected as `detected`
+    /// (only consulted when `self` is `Auto`).
+    fn resolve(self, detected: DetectedTerminator) -> &'static str {
+        match self {
+            NewlineStyle::Auto => detected.terminator(),
+            NewlineStyle::Unix => "\n",
+            NewlineStyle::Windows => "\r\n",
+            NewlineStyle::Native => {
+                if cfg!(windows) {
+                    "\r\n"
+                } else {
+                    "\n"
+                }
+            }
+        }
+    }
+}
+
+/// The terminator a file was found to predominantly use when opened.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DetectedTerminator {
+    /// `\n` only (or no newlines at all - defaults to Unix).
+    Unix,
+    /// `\r\n` only.
+    Windows,
+    /// A mix of `\r\n` and bare `\n` - the majority convention wins, but this
+    /// is reported so callers can surface it to the user.
+    Mixed { majority: MajorityTerminator },
+}
+
+/// Which convention was in the majority within a `Mixed` file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MajorityTerminator {
+    Unix,
+    Windows,
+}
+
+impl DetectedTerminator {
+    fn terminator(self) -> &'static str {
+        match self {
+            DetectedTerminator::Unix => "\n",
+            DetectedTerminator::Windows => "\r\n",
+            DetectedTerminator::Mixed {
+                majority: MajorityTerminator::Unix,
+            } => "\n",
+            DetectedTerminator::Mixed {
+                majority: MajorityTerminator::Windows,
+            } => "\r\n",
+        }
+    }
+}
+
+#[derive(Default)]
+struct TerminatorCounts {
+    crlf: usize,
+    lone_lf: usize,
+}
+
+impl AddAssign<(usize, usize)> for TerminatorCounts {
+    fn add_assign(&mut self, (crlf, lone_lf): (usize, usize)) {
+        self.crlf += crlf;
+        self.lone_lf += lone_lf;
+    }
+}
+
+/// Scan raw file content for its dominant line terminator.
+///
+/// A `\r` immediately followed by `\n` counts as one CRLF terminator; any
+/// other `\n` counts as a lone LF. The majority convention wins; a tie
+/// (including a file with no newlines at all) defaults to Unix.
+pub fn detect_terminator(content: &str) -> DetectedTerminator {
+    let bytes = content.as_bytes();
+    let mut counts = TerminatorCounts::default();
+
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'\n' {
+            if i > 0 && bytes[i - 1] == b'\r' {
+                counts += (1, 0);
+            } else {
+                counts += (0, 1);
+            }
+        }
+        i += 1;
+    }
+
+    match (counts.crlf > 0, counts.lone_lf > 0) {
+        (true, false) => DetectedTerminator::Windows,
+        (false, true) | (false, false) => DetectedTerminator::Unix,
+        (true, true) => DetectedTerminator::Mixed {
+            majority: if counts.crlf > counts.lone_lf {
+                MajorityTerminator::Windows
+            } else {
+                MajorityTerminator::Unix
+            },
+        },
+    }
+}
+
+/// Strip `\r` from every line ending so the buffer can store lines normalized
+/// to `\n`, regardless of how the file was originally terminated.
+pub fn normalize_to_lf(content: &str) -> String {
+    content.replace("\r\n", "\n")
+}
+
+/// Reattach the chosen terminator when writing a buffer back out.
+///
+/// `content` must already be `\n`-normalized (as produced by
+/// [`normalize_to_lf`]). A file that had no trailing newline when opened
+/// stays without one: this function never adds or removes a trailing
+/// terminator, it only rewrites the ones already present.
+pub fn apply_terminator(content: &str, style: NewlineStyle, detected: DetectedTerminator) -> String {
+    let terminator = style.resolve(detected);
+    if terminator == "\n" {
+        return content.to_string();
+    }
+    content.replace('\n', terminator)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_terminator_unix() {
+        assert_eq!(detect_terminator("a\nb\nc\n"), DetectedTerminator::Unix);
+        assert_eq!(detect_terminator("no newlines"), DetectedTerminator::Unix);
+    }
+
+    #[test]
+    fn test_detect_terminator_windows() {
+        assert_eq!(
+            detect_terminator("a\r\nb\r\nc\r\n"),
+            DetectedTerminator::Windows
+        );
+    }
+
+    #[test]
+    fn test_detect_terminator_mixed_picks_majority() {
+        let detected = detect_terminator("a\r\nb\r\nc\nd\r\n");
+        assert_eq!(
+            detected,
+            DetectedTerminator::Mixed {
+                majority: MajorityTerminator::Windows
+            }
+        );
+    }
+
+    #[test]
+    fn test_normalize_then_apply_round_trips_windows_file() {
+        let original = "fn main() {\r\n    foo();\r\n}\r\n";
+        let detected = detect_terminator(original);
+        let normalized = normalize_to_lf(original);
+        assert_eq!(normalized, "fn main() {\n    foo();\n}\n");
+
+        let restored = apply_terminator(&normalized, NewlineStyle::Auto, detected);
+        assert_eq!(restored, original);
+    }
+
+    #[test]
+    fn test_forced_unix_style_converts_windows_file() {
+        let original = "a\r\nb\r\n";
+        let detected = detect_terminator(original);
+        let normalized = normalize_to_lf(original);
+        let restored = apply_terminator(&normalized, NewlineStyle::Unix, detected);
+        assert_eq!(restored, "a\nb\n");
+    }
+
+    #[test]
+    fn test_trailing_newline_less_file_stays_that_way() {
+        let original = "a\r\nb"; // no trailing terminator
+        let detected = detect_terminator(original);
+        let normalized = normalize_to_lf(original);
+        assert_eq!(normalized, "a\nb");
+
+        let restored = apply_terminator(&normalized, NewlineStyle::Auto, detected);
+        assert_eq!(restored, "a\r\nb");
+    }
+}
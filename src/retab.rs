@@ -0,0 +1,168 @@
+/// Retab: convert leading indentation between tabs and spaces
+///
+/// Analogous to the coreutils `unexpand`/`expand` pair, this rewrites only the
+/// indentation region of each line (the leading whitespace before the first
+/// non-whitespace column) to match a language's `use_tabs`/`tab_size`
+/// configuration. Text after the first non-whitespace column - including
+/// alignment spaces inside string literals - is left untouched.
+use std::ops::Range;
+
+/// A single line's indentation rewrite: the byte range of its leading
+/// whitespace and the replacement text for that range.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndentEdit {
+    pub range: Range<usize>,
+    pub replacement: String,
+}
+
+/// Compute the indentation byte range for one line's content (no trailing
+/// newline). This is everything up to, but not including, the first
+/// non-space, non-tab character.
+fn leading_whitespace_len(line: &str) -> usize {
+    line.bytes()
+        .take_while(|&b| b == b' ' || b == b'\t')
+        .count()
+}
+
+/// Convert one line's leading whitespace to tabs, collapsing each run of
+/// `tab_size` leading spaces into a tab. Spaces left over that don't form a
+/// full tab stop are preserved as alignment spaces after the tabs, matching
+/// `unexpand`'s default behavior.
+fn indent_to_tabs(indent: &str, tab_size: usize) -> String {
+    if tab_size == 0 {
+        return indent.to_string();
+    }
+
+    // Expand any existing tabs to spaces first so mixed indentation is
+    // normalized consistently, then re-collapse to tabs.
+    let expanded = indent_to_spaces(indent, tab_size);
+    let full_stops = expanded.len() / tab_size;
+    let remainder = expanded.len() % tab_size;
+    format!("{}{}", "\t".repeat(full_stops), " ".repeat(remainder))
+}
+
+/// Convert one line's leading whitespace to spaces, expanding each tab to
+/// advance to the next `tab_size` column stop.
+fn indent_to_spaces(indent: &str, tab_size: usize) -> String {
+    if tab_size == 0 {
+        return indent.replace('\t', "");
+    }
+
+    let mut column = 0;
+    let mut out = String::with_capacity(indent.len() * tab_size.max(1));
+    for ch in indent.chars() {
+        match ch {
+            '\t' => {
+                let next_stop = (column / tab_size + 1) * tab_size;
+                out.push_str(&" ".repeat(next_stop - column));
+                column = next_stop;
+            }
+            ' ' => {
+                out.push(' ');
+                column += 1;
+            }
+            _ => unreachable!("leading_whitespace_len only admits tabs and spaces"),
+        }
+    }
+    out
+}
+
+/// Compute the set of indentation rewrites for `text` (a buffer or selection,
+/// as `\n`-joined lines), honoring `use_tabs`/`tab_size`. Only lines whose
+/// indentation actually changes produce an edit, so applying the result as a
+/// single batch is a no-op on lines that are already in the target style.
+///
+/// `base_offset` is the byte offset of `text`'s first line within the buffer,
+/// so the returned ranges are absolute and can be applied directly.
+pub fn retab_edits(text: &str, base_offset: usize, use_tabs: bool, tab_size: usize) -> Vec<IndentEdit> {
+    let mut edits = Vec::new();
+    let mut offset = base_offset;
+
+    for line in text.split_inclusive('\n') {
+        let line_content = line.strip_suffix('\n').unwrap_or(line);
+        let indent_len = leading_whitespace_len(line_content);
+        let indent = &line_content[..indent_len];
+
+        let replacement = if use_tabs {
+            indent_to_tabs(indent, tab_size)
+        } else {
+            indent_to_spaces(indent, tab_size)
+        };
+
+        if replacement != indent {
+            edits.push(IndentEdit {
+                range: offset..offset + indent_len,
+                replacement,
+            });
+        }
+
+        offset += line.len();
+    }
+
+    edits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retab_spaces_to_tabs() {
+        let text = "    foo();\n        bar();\n";
+        let edits = retab_edits(text, 0, true, 4);
+
+        assert_eq!(edits.len(), 2);
+        assert_eq!(edits[0].replacement, "\t");
+        assert_eq!(edits[1].replacement, "\t\t");
+    }
+
+    #[test]
+    fn test_retab_tabs_to_spaces() {
+        let text = "\tfoo();\n\t\tbar();\n";
+        let edits = retab_edits(text, 0, false, 4);
+
+        assert_eq!(edits.len(), 2);
+        assert_eq!(edits[0].replacement, "    ");
+        assert_eq!(edits[1].replacement, "        ");
+    }
+
+    #[test]
+    fn test_retab_preserves_partial_tab_stop_alignment() {
+        // 6 leading spaces at tab_size 4: one full tab stop plus 2 alignment spaces
+        let text = "      foo();\n";
+        let edits = retab_edits(text, 0, true, 4);
+
+        assert_eq!(edits.len(), 1);
+        assert_eq!(edits[0].replacement, "\t  ");
+    }
+
+    #[test]
+    fn test_retab_only_touches_leading_whitespace() {
+        // Spaces inside the literal, after the first non-whitespace column,
+        // must not be touched.
+        let text = "\tlet s = \"   padded   \";\n";
+        let edits = retab_edits(text, 0, false, 4);
+
+        assert_eq!(edits.len(), 1);
+        assert_eq!(edits[0].range, 0..1);
+        assert_eq!(edits[0].replacement, "    ");
+    }
+
+    #[test]
+    fn test_retab_skips_already_correct_lines() {
+        let text = "    foo();\n\tbar();\n";
+        let edits = retab_edits(text, 0, false, 4);
+
+        // Only the second line (tab-indented) needs converting
+        assert_eq!(edits.len(), 1);
+        assert_eq!(edits[0].replacement, "    ");
+    }
+
+    #[test]
+    fn test_retab_offsets_are_absolute_within_buffer() {
+        let text = "\tfoo();\n";
+        let edits = retab_edits(text, 100, false, 4);
+
+        assert_eq!(edits[0].range, 100..101);
+    }
+}
@@ -0,0 +1,106 @@
+/// Visual right-margin ruler
+///
+/// Borrowing rustfmt's `max_width`, this computes where a faint vertical
+/// ruler column should be drawn in the viewport, and which columns of a
+/// rendered line fall past it. Width is measured in *display* columns, so a
+/// line indented with real tabs advances to the next `tab_size` stop per
+/// tab rather than counting one column per character - matching how the
+/// text is actually laid out on screen.
+/// Compute the display column reached after expanding `text` up to (but not
+/// including) byte `upto`, honoring tab stops every `tab_size` columns.
+///
+/// This is the same expansion rule terminals and `indent_to_spaces` in
+/// `retab` use, so the ruler agrees with how tabs are actually rendered.
+pub fn display_column(text: &str, upto: usize, tab_size: usize) -> usize {
+    let mut column = 0;
+    for ch in text[..upto].chars() {
+        if ch == '\t' && tab_size > 0 {
+            column = (column / tab_size + 1) * tab_size;
+        } else {
+            column += 1;
+        }
+    }
+    column
+}
+
+/// The display column at which the ruler should be drawn for a given
+/// language's `max_width`. Identical to `max_width` today, but factored out
+/// as its own function since the ruler's screen column and the overflow
+/// threshold are conceptually distinct (e.g. a future "ruler offset" option).
+pub fn ruler_column(max_width: usize) -> usize {
+    max_width
+}
+
+/// A half-open *byte* range within `line` that lies beyond `max_width`
+/// display columns and should be flagged (e.g. rendered with a distinct
+/// background), or `None` if the line doesn't overflow. The range is in
+/// byte offsets, not display columns - convert with [`display_column`] if a
+/// caller needs the latter.
+pub fn overflow_range(line: &str, max_width: usize, tab_size: usize) -> Option<(usize, usize)> {
+    let total_columns = display_column(line, line.len(), tab_size);
+    if total_columns <= max_width {
+        return None;
+    }
+
+    // Find the byte offset where the display column first exceeds max_width.
+    let mut column = 0;
+    for (byte_idx, ch) in line.char_indices() {
+        let next_column = if ch == '\t' && tab_size > 0 {
+            (column / tab_size + 1) * tab_size
+        } else {
+            column + 1
+        };
+        if next_column > max_width {
+            return Some((byte_idx, line.len()));
+        }
+        column = next_column;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display_column_plain_text() {
+        assert_eq!(display_column("hello", 5, 4), 5);
+        assert_eq!(display_column("hello", 2, 4), 2);
+    }
+
+    #[test]
+    fn test_display_column_expands_tabs_to_next_stop() {
+        // One tab at column 0 advances to column 4 (tab_size 4)
+        assert_eq!(display_column("\tx", 1, 4), 4);
+        // A tab starting at column 2 still advances only to the next stop (4)
+        assert_eq!(display_column("  \tx", 3, 4), 4);
+    }
+
+    #[test]
+    fn test_ruler_column_matches_max_width() {
+        assert_eq!(ruler_column(100), 100);
+        assert_eq!(ruler_column(80), 80);
+    }
+
+    #[test]
+    fn test_overflow_range_none_when_within_limit() {
+        assert_eq!(overflow_range("short line", 80, 4), None);
+    }
+
+    #[test]
+    fn test_overflow_range_flags_columns_past_limit() {
+        let line = "x".repeat(90);
+        let overflow = overflow_range(&line, 80, 4).unwrap();
+        assert_eq!(overflow, (80, 90));
+    }
+
+    #[test]
+    fn test_overflow_range_accounts_for_tab_expansion() {
+        // A single leading tab (-> column 4) plus 80 chars overflows at 80
+        let line = format!("\t{}", "x".repeat(80));
+        let overflow = overflow_range(&line, 80, 4).unwrap();
+        // Tab occupies byte 0 but expands to columns 0..4; the 77th 'x'
+        // (byte index 77) starts at display column 80, the first past the limit.
+        assert_eq!(overflow.0, 77);
+    }
+}
@@ -0,0 +1,406 @@
+/// Literal-accelerated buffer search
+///
+/// Provides `find_next`/`find_prev` for F3/Shift-F3-style navigation and
+/// search highlighting; the editor's existing search does not call into it.
+/// Operates directly on `Buffer::slice` byte slices and returns byte
+/// offsets, so results feed straight into `LineAnchorManager::byte_to_line`.
+/// A single literal query is accelerated by jumping between occurrences of
+/// its rarest byte with `memchr` rather than checking the needle at every
+/// haystack position; a "find any of" query (comma-separated terms) instead
+/// builds an Aho-Corasick automaton and walks the haystack once.
+use crate::buffer::Buffer;
+use memchr::{memchr, memrchr};
+use std::collections::{HashMap, VecDeque};
+use std::ops::Range;
+
+/// Approximate per-byte frequency rank in typical source text, ASCII-
+/// weighted (space/`e`/`t` are common, control and high bytes are rare).
+/// Lower means more common; used to pick the rarest byte of a needle.
+const fn build_byte_frequency_table() -> [u8; 256] {
+    let mut table = [255u8; 256];
+    let common = b" etaoinshrdlucmfwypvbgkqjxzETAOINSHRDLUCMFWYPVBGKQJXZ0123456789_(),.;:{}[]\"'=+-*/<>!&|\n\t";
+    let mut i = 0;
+    while i < common.len() {
+        // Earlier entries in `common` are more frequent; only record the
+        // first (most significant) rank a byte is assigned.
+        if table[common[i] as usize] == 255 {
+            table[common[i] as usize] = (i.min(254)) as u8;
+        }
+        i += 1;
+    }
+    table
+}
+
+static BYTE_FREQUENCY: [u8; 256] = build_byte_frequency_table();
+
+/// The rarest byte in `needle` (highest frequency-table score), used as the
+/// anchor for `memchr`-accelerated scanning. `None` for an empty needle.
+fn rarest_byte(needle: &[u8]) -> Option<u8> {
+    needle.iter().copied().max_by_key(|&b| BYTE_FREQUENCY[b as usize])
+}
+
+/// A single literal needle, accelerated by its rarest byte.
+struct SingleLiteral {
+    needle: Vec<u8>,
+    rarest_byte: u8,
+    rarest_offset: usize,
+}
+
+impl SingleLiteral {
+    fn new(needle: &str) -> Self {
+        let needle = needle.as_bytes().to_vec();
+        // An empty needle never matches (`find_next`/`find_prev` both check
+        // `needle.is_empty()` up front) - a transient state while the user
+        // is still typing an incremental search query - so there's no real
+        // byte to anchor on; 0/0 is never read.
+        let (rarest_byte, rarest_offset) = match rarest_byte(&needle) {
+            Some(b) => (b, needle.iter().position(|&x| x == b).unwrap()),
+            None => (0, 0),
+        };
+        Self {
+            needle,
+            rarest_byte,
+            rarest_offset,
+        }
+    }
+
+    fn find_next(&self, buffer: &Buffer, from_byte: usize) -> Option<Range<usize>> {
+        if self.needle.is_empty() || from_byte >= buffer.len() {
+            return None;
+        }
+        let bytes = buffer.slice(from_byte..buffer.len()).as_bytes();
+        let mut search_from = 0;
+
+        loop {
+            let found = memchr(self.rarest_byte, &bytes[search_from..])? + search_from;
+            if found < self.rarest_offset {
+                search_from = found + 1;
+                continue;
+            }
+            let start = found - self.rarest_offset;
+            let end = start + self.needle.len();
+            if end <= bytes.len() && bytes[start..end] == self.needle[..] {
+                return Some(from_byte + start..from_byte + end);
+            }
+            search_from = found + 1;
+        }
+    }
+
+    fn find_prev(&self, buffer: &Buffer, from_byte: usize) -> Option<Range<usize>> {
+        if self.needle.is_empty() {
+            return None;
+        }
+        let bytes = buffer.slice(0..from_byte.min(buffer.len())).as_bytes();
+        let mut search_until = bytes.len();
+
+        loop {
+            let found = memrchr(self.rarest_byte, &bytes[..search_until])?;
+            if found < self.rarest_offset {
+                return None;
+            }
+            let start = found - self.rarest_offset;
+            let end = start + self.needle.len();
+            if end <= bytes.len() && bytes[start..end] == self.needle[..] {
+                return Some(start..end);
+            }
+            search_until = found;
+        }
+    }
+}
+
+/// A multi-pattern Aho-Corasick automaton for "find any of" (comma-separated
+/// term) search: one pass over the haystack locates the leftmost match of
+/// any pattern in the set.
+struct AhoCorasick {
+    patterns: Vec<Vec<u8>>,
+    goto_fn: Vec<HashMap<u8, usize>>,
+    fail: Vec<usize>,
+    /// Pattern indices whose match ends in this state (including those
+    /// inherited via failure links).
+    output: Vec<Vec<usize>>,
+}
+
+impl AhoCorasick {
+    fn new(patterns: &[String]) -> Self {
+        let patterns: Vec<Vec<u8>> = patterns.iter().map(|p| p.as_bytes().to_vec()).collect();
+        let mut goto_fn: Vec<HashMap<u8, usize>> = vec![HashMap::new()];
+        let mut output: Vec<Vec<usize>> = vec![Vec::new()];
+
+        for (pat_idx, pattern) in patterns.iter().enumerate() {
+            // An empty pattern would "match" at every position without ever
+            // stepping the automaton (it always matches at the root state),
+            // firing spuriously on every byte - skip it instead of
+            // registering a match at state 0.
+            if pattern.is_empty() {
+                continue;
+            }
+            let mut state = 0;
+            for &b in pattern {
+                state = match goto_fn[state].get(&b) {
+                    Some(&next) => next,
+                    None => {
+                        goto_fn.push(HashMap::new());
+                        output.push(Vec::new());
+                        let next = goto_fn.len() - 1;
+                        goto_fn[state].insert(b, next);
+                        next
+                    }
+                };
+            }
+            output[state].push(pat_idx);
+        }
+
+        let mut fail = vec![0usize; goto_fn.len()];
+        let mut queue: VecDeque<usize> = VecDeque::new();
+        for &next in goto_fn[0].clone().values() {
+            fail[next] = 0;
+            queue.push_back(next);
+        }
+
+        while let Some(state) = queue.pop_front() {
+            let transitions: Vec<(u8, usize)> =
+                goto_fn[state].iter().map(|(&b, &s)| (b, s)).collect();
+            for (b, next) in transitions {
+                queue.push_back(next);
+                let mut f = fail[state];
+                let fallback = loop {
+                    if let Some(&s) = goto_fn[f].get(&b) {
+                        break s;
+                    }
+                    if f == 0 {
+                        break 0;
+                    }
+                    f = fail[f];
+                };
+                fail[next] = if fallback == next { 0 } else { fallback };
+                let inherited = output[fail[next]].clone();
+                output[next].extend(inherited);
+            }
+        }
+
+        Self {
+            patterns,
+            goto_fn,
+            fail,
+            output,
+        }
+    }
+
+    /// Advance the automaton by one byte, following failure links until a
+    /// transition exists (or we fall back to the root).
+    fn step(&self, mut state: usize, byte: u8) -> usize {
+        loop {
+            if let Some(&next) = self.goto_fn[state].get(&byte) {
+                return next;
+            }
+            if state == 0 {
+                return 0;
+            }
+            state = self.fail[state];
+        }
+    }
+
+    fn max_pattern_len(&self) -> usize {
+        self.patterns.iter().map(|p| p.len()).max().unwrap_or(0)
+    }
+
+    /// Leftmost match of any pattern at or after `from_byte`.
+    fn find_next(&self, buffer: &Buffer, from_byte: usize) -> Option<Range<usize>> {
+        if from_byte >= buffer.len() {
+            return None;
+        }
+        let bytes = buffer.slice(from_byte..buffer.len()).as_bytes();
+        let max_len = self.max_pattern_len();
+
+        let mut state = 0;
+        let mut best: Option<(usize, usize)> = None;
+        let mut extend_until = None;
+
+        for (i, &b) in bytes.iter().enumerate() {
+            state = self.step(state, b);
+            for &pat_idx in &self.output[state] {
+                let len = self.patterns[pat_idx].len();
+                let end = i + 1;
+                let start = end - len;
+                let better = match best {
+                    Some((best_start, best_end)) => {
+                        start < best_start || (start == best_start && end < best_end)
+                    }
+                    None => true,
+                };
+                if better {
+                    best = Some((start, end));
+                }
+            }
+            if let (Some((start, _)), None) = (best, extend_until) {
+                // Once a match is found, keep scanning only as far as a
+                // longer, earlier-starting pattern could still reach.
+                extend_until = Some(start + max_len.saturating_sub(1));
+            }
+            if let Some(limit) = extend_until {
+                if i >= limit {
+                    break;
+                }
+            }
+        }
+
+        best.map(|(start, end)| from_byte + start..from_byte + end)
+    }
+
+    /// Rightmost match of any pattern before `from_byte`, found by scanning
+    /// forward from the start of the buffer (Aho-Corasick has no native
+    /// reverse mode) and keeping the last match that ends before the cursor.
+    fn find_prev(&self, buffer: &Buffer, from_byte: usize) -> Option<Range<usize>> {
+        let bytes = buffer.slice(0..from_byte.min(buffer.len())).as_bytes();
+
+        let mut state = 0;
+        let mut best: Option<(usize, usize)> = None;
+        for (i, &b) in bytes.iter().enumerate() {
+            state = self.step(state, b);
+            for &pat_idx in &self.output[state] {
+                let len = self.patterns[pat_idx].len();
+                let end = i + 1;
+                let start = end - len;
+                let better = match best {
+                    Some((_, best_end)) => end > best_end,
+                    None => true,
+                };
+                if better {
+                    best = Some((start, end));
+                }
+            }
+        }
+
+        best.map(|(start, end)| start..end)
+    }
+}
+
+/// Accelerated search over a `Buffer`: either a single literal needle or a
+/// "find any of" set of terms. Construct once per query and reuse across
+/// F3/Shift-F3 navigation and incremental highlight passes.
+pub enum LiteralSearcher {
+    Single(SingleLiteral),
+    MultiPattern(AhoCorasick),
+}
+
+impl LiteralSearcher {
+    /// Build a searcher for a single literal string.
+    pub fn single(needle: &str) -> Self {
+        LiteralSearcher::Single(SingleLiteral::new(needle))
+    }
+
+    /// Build a searcher that matches any of `terms` (e.g. a comma-separated
+    /// "find any of" query), returning the leftmost match of any term.
+    pub fn multi_pattern(terms: &[String]) -> Self {
+        LiteralSearcher::MultiPattern(AhoCorasick::new(terms))
+    }
+
+    /// The next match at or after `from_byte`, scanning forward.
+    pub fn find_next(&self, buffer: &Buffer, from_byte: usize) -> Option<Range<usize>> {
+        match self {
+            LiteralSearcher::Single(lit) => lit.find_next(buffer, from_byte),
+            LiteralSearcher::MultiPattern(ac) => ac.find_next(buffer, from_byte),
+        }
+    }
+
+    /// The previous match before `from_byte`, scanning backward.
+    pub fn find_prev(&self, buffer: &Buffer, from_byte: usize) -> Option<Range<usize>> {
+        match self {
+            LiteralSearcher::Single(lit) => lit.find_prev(buffer, from_byte),
+            LiteralSearcher::MultiPattern(ac) => ac.find_prev(buffer, from_byte),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rarest_byte_picks_least_common() {
+        // 'z' is far rarer in source text than 'e' or space.
+        assert_eq!(rarest_byte(b"tree"), Some(b'r'));
+        assert_eq!(rarest_byte(b"size"), Some(b'z'));
+    }
+
+    #[test]
+    fn test_rarest_byte_empty_needle_returns_none() {
+        assert_eq!(rarest_byte(b""), None);
+    }
+
+    #[test]
+    fn test_single_empty_needle_never_matches_instead_of_panicking() {
+        let buffer = Buffer::from_str_test("hello world");
+        let searcher = LiteralSearcher::single("");
+        assert_eq!(searcher.find_next(&buffer, 0), None);
+        assert_eq!(searcher.find_prev(&buffer, buffer.len()), None);
+    }
+
+    #[test]
+    fn test_multi_pattern_ignores_empty_term() {
+        let buffer = Buffer::from_str_test("hello world");
+        let searcher = LiteralSearcher::multi_pattern(&["".to_string(), "world".to_string()]);
+        let result = searcher.find_next(&buffer, 0).unwrap();
+        assert_eq!(result, 6..11);
+    }
+
+    #[test]
+    fn test_single_find_next_basic_match() {
+        let buffer = Buffer::from_str_test("the quick brown fox");
+        let searcher = LiteralSearcher::single("brown");
+        let result = searcher.find_next(&buffer, 0).unwrap();
+        assert_eq!(result, 10..15);
+    }
+
+    #[test]
+    fn test_single_find_next_skips_past_previous_match() {
+        let buffer = Buffer::from_str_test("foo foo foo");
+        let searcher = LiteralSearcher::single("foo");
+        let first = searcher.find_next(&buffer, 0).unwrap();
+        assert_eq!(first, 0..3);
+        let second = searcher.find_next(&buffer, first.end).unwrap();
+        assert_eq!(second, 4..7);
+    }
+
+    #[test]
+    fn test_single_find_prev_finds_closest_before_cursor() {
+        let buffer = Buffer::from_str_test("foo bar foo bar");
+        let searcher = LiteralSearcher::single("foo");
+        let result = searcher.find_prev(&buffer, 15).unwrap();
+        assert_eq!(result, 8..11);
+    }
+
+    #[test]
+    fn test_single_find_next_no_match_returns_none() {
+        let buffer = Buffer::from_str_test("hello world");
+        let searcher = LiteralSearcher::single("xyz");
+        assert_eq!(searcher.find_next(&buffer, 0), None);
+    }
+
+    #[test]
+    fn test_multi_pattern_finds_leftmost_of_any_term() {
+        let buffer = Buffer::from_str_test("one two three");
+        let searcher = LiteralSearcher::multi_pattern(&["three".to_string(), "two".to_string()]);
+        // "two" starts earlier than "three", even though "three" is listed first.
+        let result = searcher.find_next(&buffer, 0).unwrap();
+        assert_eq!(result, 4..7);
+    }
+
+    #[test]
+    fn test_multi_pattern_prefers_earlier_start_over_listed_order() {
+        let buffer = Buffer::from_str_test("abcdef");
+        // "cdef" is listed first and is the longer pattern, but "ab" starts
+        // earlier, so it is the leftmost match.
+        let searcher = LiteralSearcher::multi_pattern(&["cdef".to_string(), "ab".to_string()]);
+        let result = searcher.find_next(&buffer, 0).unwrap();
+        assert_eq!(result, 0..2);
+    }
+
+    #[test]
+    fn test_multi_pattern_find_prev() {
+        let buffer = Buffer::from_str_test("cat dog cat bird");
+        let searcher = LiteralSearcher::multi_pattern(&["cat".to_string(), "dog".to_string()]);
+        let result = searcher.find_prev(&buffer, 16).unwrap();
+        assert_eq!(result, 8..11);
+    }
+}
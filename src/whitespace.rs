@@ -0,0 +1,177 @@
+/// Whitespace visualization
+///
+/// Generalizes the original `show_whitespace_tabs` switch (tab -> `→`) into a
+/// richer set of independently toggleable glyphs/highlights: leading and
+/// trailing spaces, trailing whitespace at end of line, and non-breaking
+/// spaces. Each class can be turned on or off per language as well as
+/// globally, so e.g. a Makefile can keep showing significant tabs as `→`
+/// while still flagging accidental trailing whitespace.
+use ratatui::style::Color;
+
+/// Which whitespace classes to visualize and how.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WhitespaceDisplay {
+    /// Render tab characters as `→` (the original `show_whitespace_tabs`).
+    pub show_tabs: bool,
+    /// Render every space as `·` (leading, interior and trailing).
+    pub show_spaces: bool,
+    /// Highlight trailing whitespace at the end of a line with a background.
+    pub show_trailing: bool,
+    /// Render non-breaking spaces (U+00A0) with a distinct glyph.
+    pub show_nbsp: bool,
+}
+
+impl Default for WhitespaceDisplay {
+    fn default() -> Self {
+        // Matches the pre-existing default: only tabs were visualized.
+        Self {
+            show_tabs: true,
+            show_spaces: false,
+            show_trailing: false,
+            show_nbsp: false,
+        }
+    }
+}
+
+/// A single rendering substitution: replace the byte range `[start, end)`
+/// of a line with `glyph`, drawn with an optional background highlight.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WhitespaceGlyph {
+    pub start: usize,
+    pub end: usize,
+    pub glyph: &'static str,
+    pub background: Option<Color>,
+}
+
+const TAB_ARROW: &str = "→";
+const SPACE_DOT: &str = "·";
+const NBSP_GLYPH: &str = "␣";
+const TRAILING_BG: Color = Color::Red;
+
+/// Compute the glyph substitutions for one line (no trailing newline),
+/// honoring which whitespace classes `display` has enabled.
+pub fn line_glyphs(line: &str, display: &WhitespaceDisplay) -> Vec<WhitespaceGlyph> {
+    let mut glyphs = Vec::new();
+    let trailing_start = trailing_whitespace_start(line);
+
+    for (byte_idx, ch) in line.char_indices() {
+        let is_trailing = display.show_trailing && byte_idx >= trailing_start;
+        let background = if is_trailing { Some(TRAILING_BG) } else { None };
+
+        match ch {
+            '\t' if display.show_tabs => {
+                glyphs.push(WhitespaceGlyph {
+                    start: byte_idx,
+                    end: byte_idx + 1,
+                    glyph: TAB_ARROW,
+                    background,
+                });
+            }
+            ' ' if display.show_spaces || is_trailing => {
+                glyphs.push(WhitespaceGlyph {
+                    start: byte_idx,
+                    end: byte_idx + 1,
+                    glyph: SPACE_DOT,
+                    background,
+                });
+            }
+            '\u{A0}' if display.show_nbsp => {
+                glyphs.push(WhitespaceGlyph {
+                    start: byte_idx,
+                    end: byte_idx + ch.len_utf8(),
+                    glyph: NBSP_GLYPH,
+                    background,
+                });
+            }
+            _ if is_trailing => {
+                // Any other "whitespace-like" trailing byte still gets the
+                // highlight even if its own glyph class is disabled.
+                glyphs.push(WhitespaceGlyph {
+                    start: byte_idx,
+                    end: byte_idx + ch.len_utf8(),
+                    glyph: " ",
+                    background,
+                });
+            }
+            _ => {}
+        }
+    }
+
+    glyphs
+}
+
+/// The byte offset where trailing whitespace begins on `line`, or
+/// `line.len()` if there is none.
+fn trailing_whitespace_start(line: &str) -> usize {
+    line.char_indices()
+        .rev()
+        .take_while(|(_, c)| c.is_whitespace())
+        .last()
+        .map(|(idx, _)| idx)
+        .unwrap_or(line.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_only_shows_tabs() {
+        let display = WhitespaceDisplay::default();
+        let glyphs = line_glyphs("\thello world", &display);
+        assert_eq!(glyphs.len(), 1);
+        assert_eq!(glyphs[0].glyph, TAB_ARROW);
+    }
+
+    #[test]
+    fn test_show_spaces_marks_every_space() {
+        let display = WhitespaceDisplay {
+            show_spaces: true,
+            ..WhitespaceDisplay::default()
+        };
+        let glyphs = line_glyphs("a b c", &display);
+        assert_eq!(glyphs.len(), 2);
+        assert!(glyphs.iter().all(|g| g.glyph == SPACE_DOT));
+    }
+
+    #[test]
+    fn test_trailing_whitespace_is_highlighted_even_without_show_spaces() {
+        let display = WhitespaceDisplay {
+            show_trailing: true,
+            ..WhitespaceDisplay::default()
+        };
+        let glyphs = line_glyphs("code   ", &display);
+        assert_eq!(glyphs.len(), 3);
+        assert!(glyphs.iter().all(|g| g.background == Some(TRAILING_BG)));
+    }
+
+    #[test]
+    fn test_makefile_can_suppress_tab_arrows_while_flagging_trailing_spaces() {
+        // Go/Makefile-style opt-out: tabs suppressed, trailing still flagged.
+        let display = WhitespaceDisplay {
+            show_tabs: false,
+            show_trailing: true,
+            ..WhitespaceDisplay::default()
+        };
+        let glyphs = line_glyphs("\techo hi  ", &display);
+
+        // No glyph for the significant leading tab...
+        assert!(glyphs.iter().all(|g| g.start != 0));
+        // ...but the trailing run is still flagged.
+        assert!(glyphs.iter().any(|g| g.background == Some(TRAILING_BG)));
+    }
+
+    #[test]
+    fn test_nbsp_glyph_only_when_enabled() {
+        let line = "a\u{A0}b";
+        assert!(line_glyphs(line, &WhitespaceDisplay::default()).is_empty());
+
+        let display = WhitespaceDisplay {
+            show_nbsp: true,
+            ..WhitespaceDisplay::default()
+        };
+        let glyphs = line_glyphs(line, &display);
+        assert_eq!(glyphs.len(), 1);
+        assert_eq!(glyphs[0].start, 1);
+    }
+}